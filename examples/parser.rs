@@ -23,6 +23,18 @@ struct Opt {
     #[structopt(short, long)]
     json: bool,
 
+    /// Dump to the JSON format, byte for byte like Chromium's converter
+    #[structopt(long)]
+    chromium: bool,
+
+    /// Dump to a JSON Schema document
+    #[structopt(long = "json-schema")]
+    json_schema: bool,
+
+    /// Dump to an AsyncAPI document
+    #[structopt(long)]
+    asyncapi: bool,
+
     /// Dump API to Markdown documentation
     #[structopt(short, long)]
     markdown: bool,
@@ -35,7 +47,7 @@ struct Opt {
     #[structopt(long)]
     open: bool,
 
-    /// file in PDL format
+    /// file in PDL format, or in JSON format if it has the `.json` extension
     #[structopt(parse(from_os_str))]
     file: PathBuf,
 }
@@ -66,10 +78,16 @@ impl Opt {
     }
 
     fn dump_to<W: Write>(&self, w: &mut W, proto: &pdl::Protocol) -> Result<(), Error> {
-        if self.json {
+        if self.chromium {
+            write!(w, "{}", proto.to_chromium_json()?)?;
+        } else if self.asyncapi {
+            write!(w, "{:#}", proto.to_asyncapi())?;
+        } else if self.json_schema {
+            write!(w, "{:#}", proto.to_json_schema())?;
+        } else if self.json {
             write!(w, "{}", proto.to_json_pretty()?)?;
         } else if self.pdl {
-            write!(w, "{}", proto.to_string())?;
+            write!(w, "{}", proto)?;
         } else if self.markdown {
             markdown::render(w, &self.file.file_name().unwrap().to_string_lossy(), proto)?;
         }
@@ -123,7 +141,7 @@ mod markdown {
                 )?;
             }
 
-            writeln!(w, "")
+            writeln!(w)
         } else {
             Ok(())
         }
@@ -238,7 +256,7 @@ mod markdown {
                     writeln!(w, "---\n")?;
                 }
 
-                writeln!(w, "")?;
+                writeln!(w)?;
             }
 
             if !domain.events.is_empty() {
@@ -279,25 +297,13 @@ fn main() -> Result<(), Error> {
     let opt = Opt::from_args();
     debug!("opt: {:#?}", opt);
 
-    let mut f = File::open(&opt.file)?;
-    let mut s = String::new();
-    f.read_to_string(&mut s)?;
-
-    let (rest, protocol) = pdl::parse(&s).map_err(|err| {
-        format_err!(
-            "fail to parse PDL file, {}",
-            match err {
-                nom::Err::Incomplete(_) => format!("incomplete input"),
-                nom::Err::Error((_, err)) | nom::Err::Failure((_, err)) => {
-                    format!("{}", err.description())
-                }
-            }
-        )
-    })?;
+    let protocol = if opt.file.extension() == Some("json".as_ref()) {
+        pdl::Protocol::from_json_reader(File::open(&opt.file)?)
+            .map_err(|err| format_err!("fail to parse JSON file, {}", err))?
+    } else {
+        pdl::parse_file(&opt.file).map_err(|err| format_err!("fail to parse PDL file, {}", err))?
+    };
 
-    if !rest.is_empty() {
-        warn!("unexpected: {}", &rest[..1000]);
-    }
     trace!("protocol: {:#?}", protocol);
 
     opt.dump(&protocol)
@@ -0,0 +1,296 @@
+use serde_json::{json, Map, Value};
+
+use crate::json_schema::Dialect;
+use crate::*;
+
+/// The version of the AsyncAPI specification of the generated documents.
+pub const ASYNCAPI_VERSION: &str = "3.0.0";
+
+impl Protocol<'_> {
+    /// Turn the `Protocol` into an AsyncAPI document, from the point of view of a client.
+    ///
+    /// Every domain is a channel. A command is an operation sending a request with the reply
+    /// of its response, and an event an operation receiving a message. The schemas of types,
+    /// commands and events are reusable components, named like in `to_json_schema`.
+    ///
+    /// The `experimental` and `deprecated` flags are kept as `x-experimental` and `x-deprecated` extensions.
+    pub fn to_asyncapi(&self) -> Value {
+        let dialect = Dialect::ASYNCAPI;
+        let objects = Dialect::ASYNCAPI_OBJECTS;
+        let mut channels = Map::new();
+        let mut operations = Map::new();
+        let mut messages = Map::new();
+
+        for domain in &self.domains {
+            let channel = json!({ "$ref": format!("#/channels/{}", domain.name) });
+            let mut channel_messages = Map::new();
+            let mut add_message = |key: String, message: Value| {
+                let name = format!("{}.{}", domain.name, key);
+                let reference = json!({
+                    "$ref": format!("#/channels/{}/messages/{}", domain.name, key)
+                });
+
+                channel_messages.insert(
+                    key,
+                    json!({ "$ref": format!("#/components/messages/{}", name) }),
+                );
+                messages.insert(name, message);
+
+                reference
+            };
+
+            for cmd in &domain.commands {
+                let method = dialect.name(domain, &cmd.name);
+                let mut request = message(
+                    &method,
+                    json!({
+                        "type": "object",
+                        "properties": {
+                            "id": { "type": "integer" },
+                            "method": { "const": method },
+                            "params": dialect.reference(&format!("{}.parameters", method)),
+                        },
+                        "required": if cmd.parameters.iter().any(|param| !param.optional) {
+                            json!(["id", "method", "params"])
+                        } else {
+                            json!(["id", "method"])
+                        },
+                    }),
+                );
+                let mut response = message(
+                    &method,
+                    json!({
+                        "type": "object",
+                        "properties": {
+                            "id": { "type": "integer" },
+                            "result": if cmd.returns.is_empty() {
+                                json!({ "type": "object" })
+                            } else {
+                                dialect.reference(&format!("{}.returns", method))
+                            },
+                        },
+                        "required": ["id", "result"],
+                    }),
+                );
+
+                for msg in &mut [&mut request, &mut response] {
+                    msg["correlationId"] = json!({ "location": "$message.payload#/id" });
+                    objects.annotate(msg, &cmd.description, cmd.experimental, cmd.deprecated);
+                }
+
+                let request = add_message(format!("{}.request", cmd.name), request);
+                let response = add_message(format!("{}.response", cmd.name), response);
+                let mut operation = json!({
+                    "action": "send",
+                    "channel": channel,
+                    "messages": [request],
+                    "reply": {
+                        "channel": channel,
+                        "messages": [response],
+                    },
+                });
+
+                objects.annotate(
+                    &mut operation,
+                    &cmd.description,
+                    cmd.experimental,
+                    cmd.deprecated,
+                );
+                operations.insert(method, operation);
+            }
+
+            for evt in &domain.events {
+                let method = dialect.name(domain, &evt.name);
+                let mut event = message(
+                    &method,
+                    json!({
+                        "type": "object",
+                        "properties": {
+                            "method": { "const": method },
+                            "params": dialect.reference(&dialect.event(domain, &evt.name)),
+                        },
+                        "required": if evt.parameters.iter().any(|param| !param.optional) {
+                            json!(["method", "params"])
+                        } else {
+                            json!(["method"])
+                        },
+                    }),
+                );
+
+                objects.annotate(
+                    &mut event,
+                    &evt.description,
+                    evt.experimental,
+                    evt.deprecated,
+                );
+
+                let event = add_message(evt.name.to_string(), event);
+                let mut operation = json!({
+                    "action": "receive",
+                    "channel": channel,
+                    "messages": [event],
+                });
+
+                objects.annotate(
+                    &mut operation,
+                    &evt.description,
+                    evt.experimental,
+                    evt.deprecated,
+                );
+                operations.insert(method, operation);
+            }
+
+            let mut channel = json!({
+                "address": null,
+                "messages": channel_messages,
+            });
+
+            objects.annotate(
+                &mut channel,
+                &domain.description,
+                domain.experimental,
+                domain.deprecated,
+            );
+            channels.insert(domain.name.to_string(), channel);
+        }
+
+        json!({
+            "asyncapi": ASYNCAPI_VERSION,
+            "info": {
+                "title": "Chrome DevTools Protocol",
+                "version": format!("{}.{}", self.version.major, self.version.minor),
+            },
+            "defaultContentType": "application/json",
+            "channels": channels,
+            "operations": operations,
+            "components": {
+                "schemas": dialect.schemas(self),
+                "messages": messages,
+            },
+        })
+    }
+}
+
+/// A message named after the `method` carrying the `payload`.
+fn message(method: &str, payload: Value) -> Value {
+    json!({
+        "name": method,
+        "payload": payload,
+    })
+}
+
+#[cfg(all(test, feature = "parse"))]
+mod tests {
+    use super::*;
+
+    const PDL: &str = r#"version
+  major 1
+  minor 3
+
+# Actions and events related to the inspected page.
+domain Page
+  type FrameId extends string
+
+  # Navigates current page to the given URL.
+  experimental command navigate
+    parameters
+      string url
+    returns
+      FrameId frameId
+
+  command stopLoading
+
+  deprecated event frameNavigated
+"#;
+
+    #[test]
+    fn asyncapi() {
+        let doc = crate::parse_str(PDL).unwrap().to_asyncapi();
+
+        assert_eq!(doc["asyncapi"], ASYNCAPI_VERSION);
+        assert_eq!(doc["info"]["version"], "1.3");
+        assert_eq!(
+            doc["channels"]["Page"],
+            json!({
+                "address": null,
+                "description": "Actions and events related to the inspected page.",
+                "messages": {
+                    "navigate.request": { "$ref": "#/components/messages/Page.navigate.request" },
+                    "navigate.response": { "$ref": "#/components/messages/Page.navigate.response" },
+                    "stopLoading.request": { "$ref": "#/components/messages/Page.stopLoading.request" },
+                    "stopLoading.response": { "$ref": "#/components/messages/Page.stopLoading.response" },
+                    "frameNavigated": { "$ref": "#/components/messages/Page.frameNavigated" }
+                }
+            })
+        );
+        assert_eq!(
+            doc["operations"]["Page.navigate"],
+            json!({
+                "action": "send",
+                "channel": { "$ref": "#/channels/Page" },
+                "messages": [{ "$ref": "#/channels/Page/messages/navigate.request" }],
+                "reply": {
+                    "channel": { "$ref": "#/channels/Page" },
+                    "messages": [{ "$ref": "#/channels/Page/messages/navigate.response" }]
+                },
+                "description": "Navigates current page to the given URL.",
+                "x-experimental": true
+            })
+        );
+        assert_eq!(
+            doc["operations"]["Page.frameNavigated"],
+            json!({
+                "action": "receive",
+                "channel": { "$ref": "#/channels/Page" },
+                "messages": [{ "$ref": "#/channels/Page/messages/frameNavigated" }],
+                "x-deprecated": true
+            })
+        );
+        assert_eq!(
+            doc["components"]["messages"]["Page.navigate.request"],
+            json!({
+                "name": "Page.navigate",
+                "correlationId": { "location": "$message.payload#/id" },
+                "description": "Navigates current page to the given URL.",
+                "x-experimental": true,
+                "payload": {
+                    "type": "object",
+                    "properties": {
+                        "id": { "type": "integer" },
+                        "method": { "const": "Page.navigate" },
+                        "params": { "$ref": "#/components/schemas/Page.navigate.parameters" }
+                    },
+                    "required": ["id", "method", "params"]
+                }
+            })
+        );
+        assert_eq!(
+            doc["components"]["schemas"]["Page.navigate.returns"],
+            json!({
+                "type": "object",
+                "description": "Navigates current page to the given URL.",
+                "x-experimental": true,
+                "properties": {
+                    "frameId": { "$ref": "#/components/schemas/Page.FrameId" }
+                },
+                "required": ["frameId"]
+            })
+        );
+        assert_eq!(
+            doc["components"]["messages"]["Page.stopLoading.response"]["payload"]["properties"]
+                ["result"],
+            json!({ "type": "object" })
+        );
+        assert!(doc["components"]["schemas"]
+            .get("Page.stopLoading.returns")
+            .is_none());
+        assert_eq!(
+            doc["components"]["messages"]["Page.frameNavigated"]["payload"]["properties"]["params"],
+            json!({ "$ref": "#/components/schemas/Page.frameNavigated.event" })
+        );
+        assert_eq!(
+            doc["components"]["messages"]["Page.frameNavigated"]["payload"]["required"],
+            json!(["method"])
+        );
+    }
+}
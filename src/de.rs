@@ -0,0 +1,598 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::io;
+
+use serde::{
+    de::{self, Visitor},
+    Deserialize, Deserializer,
+};
+
+use crate::*;
+
+impl Protocol<'static> {
+    /// Deserialize a `Protocol` from a string of JSON in the format of Chromium's `browser_protocol.json`.
+    pub fn from_json(s: &str) -> serde_json::Result<Protocol<'static>> {
+        serde_json::from_str(s)
+    }
+
+    /// Deserialize a `Protocol` from a reader of JSON in the format of Chromium's `browser_protocol.json`.
+    pub fn from_json_reader<R: io::Read>(reader: R) -> serde_json::Result<Protocol<'static>> {
+        serde_json::from_reader(reader)
+    }
+}
+
+impl<'de> Deserialize<'de> for Protocol<'static> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        RawProtocol::deserialize(deserializer)?
+            .into_protocol()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// The JSON objects as Chromium lays them out, before they are checked and turned into the model.
+#[derive(Deserialize)]
+struct RawProtocol {
+    version: RawVersion,
+    #[serde(default)]
+    domains: Vec<RawDomain>,
+}
+
+#[derive(Deserialize)]
+struct RawVersion {
+    #[serde(deserialize_with = "deserialize_usize")]
+    major: usize,
+    #[serde(deserialize_with = "deserialize_usize")]
+    minor: usize,
+}
+
+#[derive(Deserialize)]
+struct RawDomain {
+    domain: String,
+    description: Option<String>,
+    #[serde(default)]
+    experimental: bool,
+    #[serde(default)]
+    deprecated: bool,
+    #[serde(default)]
+    annotations: Vec<String>,
+    #[serde(default)]
+    dependencies: Vec<String>,
+    #[serde(default)]
+    types: Vec<RawTypeDef>,
+    #[serde(default)]
+    commands: Vec<RawCommand>,
+    #[serde(default)]
+    events: Vec<RawEvent>,
+}
+
+#[derive(Deserialize)]
+struct RawTypeDef {
+    id: String,
+    description: Option<String>,
+    #[serde(default)]
+    experimental: bool,
+    #[serde(default)]
+    deprecated: bool,
+    #[serde(default)]
+    annotations: Vec<String>,
+    #[serde(flatten)]
+    ty: RawType,
+    properties: Option<Vec<RawParam>>,
+}
+
+#[derive(Deserialize)]
+struct RawCommand {
+    name: String,
+    description: Option<String>,
+    #[serde(default)]
+    experimental: bool,
+    #[serde(default)]
+    deprecated: bool,
+    #[serde(default)]
+    annotations: Vec<String>,
+    redirect: Option<String>,
+    #[serde(default)]
+    parameters: Vec<RawParam>,
+    #[serde(default)]
+    returns: Vec<RawParam>,
+}
+
+#[derive(Deserialize)]
+struct RawEvent {
+    name: String,
+    description: Option<String>,
+    #[serde(default)]
+    experimental: bool,
+    #[serde(default)]
+    deprecated: bool,
+    #[serde(default)]
+    annotations: Vec<String>,
+    #[serde(default)]
+    parameters: Vec<RawParam>,
+}
+
+#[derive(Deserialize)]
+struct RawParam {
+    name: String,
+    description: Option<String>,
+    #[serde(default)]
+    experimental: bool,
+    #[serde(default)]
+    deprecated: bool,
+    #[serde(default)]
+    optional: bool,
+    #[serde(default)]
+    annotations: Vec<String>,
+    #[serde(flatten)]
+    ty: RawType,
+}
+
+/// The `type`, `$ref`, `enum` and `items` keys shared by type definitions, parameters and array items.
+#[derive(Deserialize)]
+struct RawType {
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    #[serde(rename = "$ref")]
+    reference: Option<String>,
+    #[serde(rename = "enum")]
+    variants: Option<Vec<String>>,
+    items: Option<Box<RawType>>,
+}
+
+impl RawProtocol {
+    fn into_protocol(self) -> Result<Protocol<'static>, String> {
+        Ok(Protocol {
+            description: Default::default(),
+            version: Version {
+                major: self.version.major,
+                minor: self.version.minor,
+                description: Default::default(),
+                ..Default::default()
+            },
+            domains: self
+                .domains
+                .into_iter()
+                .map(RawDomain::into_domain)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl RawDomain {
+    fn into_domain(self) -> Result<Domain<'static>, String> {
+        let name = self.domain;
+        let context = |err: String| format!("{} in domain `{}`", err, name);
+
+        Ok(Domain {
+            description: description(self.description),
+            experimental: self.experimental,
+            deprecated: self.deprecated,
+            annotations: owned(self.annotations),
+            dependencies: self.dependencies.into_iter().map(Dependency::new).collect(),
+            types: self
+                .types
+                .into_iter()
+                .map(RawTypeDef::into_type_def)
+                .collect::<Result<_, _>>()
+                .map_err(context)?,
+            commands: self
+                .commands
+                .into_iter()
+                .map(RawCommand::into_command)
+                .collect::<Result<_, _>>()
+                .map_err(context)?,
+            events: self
+                .events
+                .into_iter()
+                .map(RawEvent::into_event)
+                .collect::<Result<_, _>>()
+                .map_err(context)?,
+            name: name.into(),
+            span: Default::default(),
+        })
+    }
+}
+
+impl RawTypeDef {
+    fn into_type_def(self) -> Result<TypeDef<'static>, String> {
+        let id = self.id;
+        let context = |err: String| format!("{} of type `{}`", err, id);
+        let mut ty = self.ty;
+
+        // the `enum` and `properties` of a type definition belong to the type, not to what it extends
+        let item = match (ty.variants.take(), self.properties) {
+            (Some(_), Some(_)) => return Err(context("both `enum` and `properties`".into())),
+            (Some(variants), None) => Some(Item::Enum(self::variants(variants))),
+            (None, Some(props)) => Some(Item::Properties(params(props).map_err(context)?)),
+            (None, None) => None,
+        };
+
+        let extends = match (ty.into_type().map_err(context)?, &item) {
+            // an `array of enum` leaves its variants to the `enum` of the type definition
+            (Type::ArrayOf(ref items), Some(Item::Enum(_))) if **items == Type::String => {
+                Type::ArrayOf(Box::new(Type::Enum(vec![])))
+            }
+            (extends, _) => extends,
+        };
+
+        Ok(TypeDef {
+            description: description(self.description),
+            experimental: self.experimental,
+            deprecated: self.deprecated,
+            annotations: owned(self.annotations),
+            extends,
+            item,
+            id: id.into(),
+            span: Default::default(),
+        })
+    }
+}
+
+impl RawCommand {
+    fn into_command(self) -> Result<Command<'static>, String> {
+        let name = self.name;
+        let context = |err: String| format!("{} of command `{}`", err, name);
+
+        Ok(Command {
+            description: description(self.description),
+            experimental: self.experimental,
+            deprecated: self.deprecated,
+            annotations: owned(self.annotations),
+            redirect: self.redirect.map(|to| Redirect {
+                description: Default::default(),
+                to: to.into(),
+                span: Default::default(),
+            }),
+            parameters: params(self.parameters).map_err(context)?,
+            returns: params(self.returns).map_err(context)?,
+            name: name.into(),
+            span: Default::default(),
+        })
+    }
+}
+
+impl RawEvent {
+    fn into_event(self) -> Result<Event<'static>, String> {
+        let name = self.name;
+        let context = |err: String| format!("{} of event `{}`", err, name);
+
+        Ok(Event {
+            description: description(self.description),
+            experimental: self.experimental,
+            deprecated: self.deprecated,
+            annotations: owned(self.annotations),
+            parameters: params(self.parameters).map_err(context)?,
+            name: name.into(),
+            span: Default::default(),
+        })
+    }
+}
+
+impl RawParam {
+    fn into_param(self) -> Result<Param<'static>, String> {
+        let name = self.name;
+
+        Ok(Param {
+            description: description(self.description),
+            experimental: self.experimental,
+            deprecated: self.deprecated,
+            optional: self.optional,
+            annotations: owned(self.annotations),
+            ty: self
+                .ty
+                .into_type()
+                .map_err(|err| format!("{} of `{}`", err, name))?,
+            name: name.into(),
+            span: Default::default(),
+        })
+    }
+}
+
+impl RawType {
+    fn into_type(self) -> Result<Type<'static>, String> {
+        if let Some(id) = self.reference {
+            return Ok(Type::Ref(id.into()));
+        }
+
+        let kind = self.kind.ok_or("missing `type` or `$ref`")?;
+
+        Ok(match (kind.as_str(), self.variants) {
+            ("string", Some(variants)) => Type::Enum(self::variants(variants)),
+            ("array", variants) => {
+                let items = self.items.ok_or("missing `items` of array")?.into_type()?;
+
+                // Chromium puts the `enum` of an array of strings next to its `items`
+                match (items, variants) {
+                    (Type::String, Some(variants)) => {
+                        Type::ArrayOf(Box::new(Type::Enum(self::variants(variants))))
+                    }
+                    (items, None) => Type::ArrayOf(Box::new(items)),
+                    (_, Some(_)) => return Err("`enum` of array of non-string items".into()),
+                }
+            }
+            (_, Some(_)) => return Err(format!("`enum` of non-string type `{}`", kind)),
+            ("integer", None) => Type::Integer,
+            ("number", None) => Type::Number,
+            ("boolean", None) => Type::Boolean,
+            ("string", None) => Type::String,
+            ("object", None) => Type::Object,
+            ("any", None) => Type::Any,
+            ("binary", None) => Type::Binary,
+            (_, None) => return Err(format!("unknown type `{}`", kind)),
+        })
+    }
+}
+
+fn description(description: Option<String>) -> Description<'static> {
+    Description(
+        description
+            .iter()
+            .filter(|s| !s.is_empty())
+            .flat_map(|s| s.split('\n'))
+            .map(|line| Cow::Owned(line.to_owned()))
+            .collect(),
+    )
+}
+
+fn owned(names: Vec<String>) -> Vec<Cow<'static, str>> {
+    names.into_iter().map(Cow::Owned).collect()
+}
+
+fn variants(names: Vec<String>) -> Vec<Variant<'static>> {
+    names.into_iter().map(Variant::new).collect()
+}
+
+fn params(params: Vec<RawParam>) -> Result<Vec<Param<'static>>, String> {
+    params.into_iter().map(RawParam::into_param).collect()
+}
+
+/// Chromium encodes the version numbers as strings, while older files use numbers.
+fn deserialize_usize<'de, D>(deserializer: D) -> Result<usize, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct UsizeVisitor;
+
+    impl<'de> Visitor<'de> for UsizeVisitor {
+        type Value = usize;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a number or a string of a number")
+        }
+
+        fn visit_u64<E: de::Error>(self, n: u64) -> Result<usize, E> {
+            Ok(n as usize)
+        }
+
+        fn visit_str<E: de::Error>(self, s: &str) -> Result<usize, E> {
+            s.parse()
+                .map_err(|_| E::invalid_value(de::Unexpected::Str(s), &self))
+        }
+    }
+
+    deserializer.deserialize_any(UsizeVisitor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "parse")]
+    const JSON: &str = r#"{
+    "version": { "major": "1", "minor": "3" },
+    "domains": [
+        {
+            "domain": "Page",
+            "description": "Actions and events related to the inspected page.",
+            "dependencies": ["DOM"],
+            "types": [
+                {
+                    "id": "FrameId",
+                    "description": "Unique frame identifier.",
+                    "type": "string"
+                },
+                {
+                    "id": "TransitionType",
+                    "experimental": true,
+                    "type": "string",
+                    "enum": ["link", "typed"]
+                },
+                {
+                    "id": "Frame",
+                    "type": "object",
+                    "properties": [
+                        { "name": "id", "$ref": "FrameId" },
+                        { "name": "urls", "optional": true, "type": "array", "items": { "type": "string" } },
+                        { "name": "features", "type": "array", "items": { "type": "string" }, "enum": ["a", "b"] }
+                    ]
+                }
+            ],
+            "commands": [
+                {
+                    "name": "navigate",
+                    "description": "Navigates current page to the given URL.\nThe frame is optional.",
+                    "parameters": [
+                        { "name": "url", "type": "string" },
+                        { "name": "transitionType", "optional": true, "$ref": "TransitionType" }
+                    ],
+                    "returns": [{ "name": "frameId", "$ref": "FrameId" }]
+                },
+                {
+                    "name": "setDeviceMetricsOverride",
+                    "deprecated": true,
+                    "redirect": "Emulation"
+                }
+            ],
+            "events": [
+                {
+                    "name": "frameNavigated",
+                    "parameters": [{ "name": "frame", "$ref": "Frame" }]
+                }
+            ]
+        }
+    ]
+}"#;
+
+    #[cfg(feature = "parse")]
+    #[test]
+    fn deserialize_protocol() {
+        let protocol = Protocol::from_json(JSON).unwrap();
+
+        assert_eq!(
+            protocol,
+            crate::parse_str(
+                r#"version
+  major 1
+  minor 3
+
+# Actions and events related to the inspected page.
+domain Page
+  depends on DOM
+
+  # Unique frame identifier.
+  type FrameId extends string
+
+  experimental type TransitionType extends string
+    enum
+      link
+      typed
+
+  type Frame extends object
+    properties
+      FrameId id
+      optional array of string urls
+      array of enum features
+        a
+        b
+
+  # Navigates current page to the given URL.
+  # The frame is optional.
+  command navigate
+    parameters
+      string url
+      optional TransitionType transitionType
+    returns
+      FrameId frameId
+
+  deprecated command setDeviceMetricsOverride
+    redirect Emulation
+
+  event frameNavigated
+    parameters
+      Frame frame
+"#
+            )
+            .unwrap()
+        );
+    }
+
+    #[cfg(all(feature = "parse", feature = "to_json"))]
+    #[test]
+    fn deserialize_serialized() {
+        let source = r#"# Copyright.
+
+# The version.
+version
+  major 1
+  minor 3
+
+experimental internal domain Storage
+  # For the frame tree.
+  depends on DOM
+
+  command clearDataForOrigin
+    # Moved.
+    redirect Emulation
+    parameters
+      array of enum types
+        cookies
+        local_storage
+      optional array of Frame frames
+"#;
+        let protocol = crate::parse_str(source).unwrap();
+        let json = protocol.to_json().unwrap();
+        let mut expected = protocol.clone();
+
+        // JSON has no slot for the comments of the protocol, its version, dependencies and redirects
+        expected.description = Default::default();
+        expected.version.description = Default::default();
+        expected.domains[0].dependencies[0].description = Default::default();
+        expected.domains[0].commands[0]
+            .redirect
+            .as_mut()
+            .unwrap()
+            .description = Default::default();
+
+        assert_eq!(Protocol::from_json(&json).unwrap(), expected);
+    }
+
+    #[cfg(all(feature = "parse", feature = "to_json"))]
+    #[test]
+    fn deserialize_chromium_array_of_enum() {
+        let source = r#"version
+  major 1
+  minor 3
+
+domain Emulation
+  type MediaTypes extends array of enum
+    enum
+      screen
+      print
+
+  command setEmulatedMedia
+    parameters
+      optional array of enum features
+        prefers-color-scheme
+        prefers-reduced-motion
+"#;
+        let protocol = crate::parse_str(source).unwrap();
+        let json = protocol.to_chromium_json().unwrap();
+
+        assert_eq!(Protocol::from_json(&json).unwrap(), protocol);
+    }
+
+    #[test]
+    fn deserialize_version() {
+        let json = r#"{ "version": { "major": 1, "minor": "4" } }"#;
+
+        assert_eq!(
+            Protocol::from_json(json).unwrap().version,
+            Version::new(1, 4)
+        );
+        assert_eq!(
+            Protocol::from_json(r#"{ "version": { "major": "one", "minor": "4" } }"#)
+                .unwrap_err()
+                .to_string(),
+            "invalid value: string \"one\", expected a number or a string of a number at line 1 column 29"
+        );
+    }
+
+    #[test]
+    fn deserialize_errors() {
+        let err = |json: &str| Protocol::from_json(json).unwrap_err().to_string();
+
+        assert_eq!(
+            err(r#"{ "version": { "major": "1", "minor": "3" }, "domains": [
+                { "domain": "Page", "events": [{ "name": "loaded", "parameters": [{ "name": "time", "type": "date" }] }] }
+            ] }"#),
+            "unknown type `date` of `time` of event `loaded` in domain `Page`"
+        );
+        assert_eq!(
+            err(r#"{ "version": { "major": "1", "minor": "3" }, "domains": [
+                { "domain": "Page", "types": [{ "id": "Frames", "type": "array" }] }
+            ] }"#),
+            "missing `items` of array of type `Frames` in domain `Page`"
+        );
+        assert_eq!(
+            err(r#"{ "version": { "major": "1", "minor": "3" }, "domains": [
+                { "domain": "Page", "events": [{ "name": "loaded", "parameters": [
+                    { "name": "frames", "type": "array", "items": { "$ref": "Frame" }, "enum": ["main"] }
+                ] }] }
+            ] }"#),
+            "`enum` of array of non-string items of `frames` of event `loaded` in domain `Page`"
+        );
+    }
+}
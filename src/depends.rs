@@ -0,0 +1,294 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::resolve::split;
+use crate::*;
+
+impl<'a> Protocol<'a> {
+    /// Check the `depends on` declarations of every domain against the types it refers to.
+    ///
+    /// A reference to a type of another domain which isn't declared, a dependency on a domain
+    /// which isn't defined, or a dependency cycle, is an error; a declared dependency which is
+    /// never referred to is a warning.
+    pub fn check_dependencies(&self) -> Vec<Diagnostic> {
+        let graph = Graph::new(self);
+        let names = self
+            .domains
+            .iter()
+            .map(|domain| domain.name.as_ref())
+            .collect::<HashSet<_>>();
+        let mut diagnostics = vec![];
+
+        for domain in &self.domains {
+            let refs = references(domain);
+
+            for &(reference, span) in &refs {
+                let (target, _) = split(&domain.name, reference);
+
+                if target != domain.name
+                    && !domain.dependencies.iter().any(|dep| dep.name == target)
+                {
+                    diagnostics.push(Diagnostic::error(
+                        "undeclared-dependency",
+                        format!(
+                            "domain `{}` refers to `{}` but doesn't depend on `{}`",
+                            domain.name, reference, target
+                        ),
+                        span,
+                    ));
+                }
+            }
+
+            for dep in &domain.dependencies {
+                if !names.contains(dep.name.as_ref()) {
+                    diagnostics.push(Diagnostic::error(
+                        "unknown-dependency",
+                        format!(
+                            "domain `{}` depends on unknown domain `{}`",
+                            domain.name, dep.name
+                        ),
+                        dep.span,
+                    ));
+                } else if !refs
+                    .iter()
+                    .any(|&(reference, _)| split(&domain.name, reference).0 == dep.name)
+                {
+                    diagnostics.push(Diagnostic::warning(
+                        "unused-dependency",
+                        format!(
+                            "domain `{}` depends on `{}` but doesn't refer to it",
+                            domain.name, dep.name
+                        ),
+                        dep.span,
+                    ));
+                }
+            }
+        }
+
+        for cycle in graph.walk().1 {
+            let names = cycle
+                .iter()
+                .map(|&i| format!("`{}`", self.domains[i].name))
+                .collect::<Vec<_>>();
+
+            diagnostics.push(Diagnostic::error(
+                "dependency-cycle",
+                format!("dependency cycle {}", names.join(" -> ")),
+                self.domains[cycle[0]].span,
+            ));
+        }
+
+        diagnostics
+    }
+
+    /// The domains ordered so that every domain comes after the domains it depends on.
+    ///
+    /// A domain depends on the domains in its `depends on` declarations and on those it refers to,
+    /// otherwise the domains keep their order. When the dependencies have a cycle,
+    /// the domains of the first cycle found are returned as the error, the first one repeated at the end.
+    pub fn dependency_order(&self) -> Result<Vec<&Domain<'a>>, Vec<&Domain<'a>>> {
+        let (order, cycles) = Graph::new(self).walk();
+
+        match cycles.into_iter().next() {
+            Some(cycle) => Err(cycle.into_iter().map(|i| &self.domains[i]).collect()),
+            None => Ok(order.into_iter().map(|i| &self.domains[i]).collect()),
+        }
+    }
+}
+
+/// The type references of a domain, with the span of the node which has them.
+fn references<'p>(domain: &'p Domain) -> Vec<(&'p str, Span)> {
+    domain
+        .item_references()
+        .map(|item_ref| (item_ref.reference, item_ref.span()))
+        .collect()
+}
+
+/// The domains of a `Protocol` by index, with the domains each of them depends on.
+struct Graph {
+    edges: Vec<Vec<usize>>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    Unvisited,
+    Visiting,
+    Visited,
+}
+
+impl Graph {
+    fn new(protocol: &Protocol) -> Self {
+        let mut indexes = HashMap::new();
+
+        for (i, domain) in protocol.domains.iter().enumerate() {
+            indexes.entry(domain.name.as_ref()).or_insert(i);
+        }
+
+        let edges = protocol
+            .domains
+            .iter()
+            .map(|domain| {
+                let declared = domain.dependencies.iter().map(|dep| dep.name.as_ref());
+                let referred = references(domain)
+                    .into_iter()
+                    .map(|(reference, _)| split(&domain.name, reference).0)
+                    .filter(|&name| name != domain.name);
+                let mut edges = vec![];
+
+                for name in declared.chain(referred) {
+                    if let Some(&i) = indexes.get(name) {
+                        if !edges.contains(&i) {
+                            edges.push(i);
+                        }
+                    }
+                }
+
+                edges
+            })
+            .collect();
+
+        Graph { edges }
+    }
+
+    /// Walk the graph depth first, returning the domains in dependency order and the cycles found.
+    fn walk(&self) -> (Vec<usize>, Vec<Vec<usize>>) {
+        let mut states = vec![State::Unvisited; self.edges.len()];
+        let mut stack = vec![];
+        let mut order = vec![];
+        let mut cycles = vec![];
+
+        for i in 0..self.edges.len() {
+            self.visit(i, &mut states, &mut stack, &mut order, &mut cycles);
+        }
+
+        (order, cycles)
+    }
+
+    fn visit(
+        &self,
+        i: usize,
+        states: &mut [State],
+        stack: &mut Vec<usize>,
+        order: &mut Vec<usize>,
+        cycles: &mut Vec<Vec<usize>>,
+    ) {
+        match states[i] {
+            State::Visited => {}
+            State::Visiting => {
+                let start = stack.iter().position(|&j| j == i).unwrap();
+                let mut cycle = stack[start..].to_vec();
+
+                cycle.push(i);
+                cycles.push(cycle);
+            }
+            State::Unvisited => {
+                states[i] = State::Visiting;
+                stack.push(i);
+
+                for &j in &self.edges[i] {
+                    self.visit(j, states, stack, order, cycles);
+                }
+
+                stack.pop();
+                states[i] = State::Visited;
+                order.push(i);
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "parse"))]
+mod tests {
+    use super::*;
+
+    fn names<'p>(domains: Vec<&'p Domain>) -> Vec<&'p str> {
+        domains.iter().map(|domain| domain.name.as_ref()).collect()
+    }
+
+    #[test]
+    fn check_dependencies() {
+        let protocol = crate::parse_str(
+            r#"version
+  major 1
+  minor 3
+
+domain Page
+  depends on Network
+  depends on IO
+  depends on Storage
+
+  type FrameId extends string
+
+  type Frame extends object
+    properties
+      FrameId id
+      Network.LoaderId loaderId
+      array of Runtime.RemoteObject objects
+
+domain Network
+  depends on Page
+
+  type LoaderId extends string
+
+  event requestWillBeSent
+    parameters
+      Page.FrameId frameId
+
+domain IO
+
+domain Runtime
+  type RemoteObject extends object
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            protocol
+                .check_dependencies()
+                .iter()
+                .map(|diag| diag.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "16:7: error[undeclared-dependency]: domain `Page` refers to `Runtime.RemoteObject` but doesn't depend on `Runtime`",
+                "7:3: warning[unused-dependency]: domain `Page` depends on `IO` but doesn't refer to it",
+                "8:3: error[unknown-dependency]: domain `Page` depends on unknown domain `Storage`",
+                "5:1: error[dependency-cycle]: dependency cycle `Page` -> `Network` -> `Page`",
+            ]
+        );
+        assert_eq!(
+            protocol.dependency_order().map_err(names),
+            Err(vec!["Page", "Network", "Page"])
+        );
+    }
+
+    #[test]
+    fn dependency_order() {
+        let protocol = crate::parse_str(
+            r#"version
+  major 1
+  minor 3
+
+domain Page
+  depends on Network
+  depends on DOM
+
+domain DOM
+  depends on Runtime
+
+  type NodeId extends integer
+
+domain Network
+  type Headers extends object
+    properties
+      DOM.NodeId nodeId
+
+domain Runtime
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            protocol.dependency_order().map(names),
+            Ok(vec!["Runtime", "DOM", "Network", "Page"])
+        );
+    }
+}
@@ -0,0 +1,73 @@
+use std::fmt;
+
+use crate::Span;
+
+/// How serious a `Diagnostic` is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Warning => f.write_str("warning"),
+            Severity::Error => f.write_str("error"),
+        }
+    }
+}
+
+/// A problem found in a PDL source or `Protocol`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// A short and stable identifier of the kind of problem, e.g. `syntax`.
+    pub code: &'static str,
+    pub message: String,
+    /// Where the problem is in the PDL source.
+    pub span: Span,
+}
+
+impl Diagnostic {
+    pub fn error<S: Into<String>>(code: &'static str, message: S, span: Span) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            code,
+            message: message.into(),
+            span,
+        }
+    }
+
+    pub fn warning<S: Into<String>>(code: &'static str, message: S, span: Span) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            code,
+            message: message.into(),
+            span,
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// Turn the diagnostic into a warning, e.g. for an `unknown-redirect` to a domain
+    /// of another protocol, like `js_protocol.pdl` for `browser_protocol.pdl`.
+    pub fn downgrade(self) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            ..self
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}[{}]: {}",
+            self.span.line, self.span.column, self.severity, self.code, self.message
+        )
+    }
+}
@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use std::fmt;
 
 use crate::*;
@@ -9,7 +10,11 @@ fn indented<D: fmt::Display>(data: D) -> indented::Indented<D, indented::Space2>
 impl fmt::Display for Description<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         for comment in &self.0 {
-            writeln!(f, "# {}", comment)?;
+            if comment.is_empty() {
+                writeln!(f, "#")?;
+            } else {
+                writeln!(f, "# {}", comment)?;
+            }
         }
 
         Ok(())
@@ -20,6 +25,11 @@ impl fmt::Display for Protocol<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         if !self.description.is_empty() {
             write!(f, "{}", self.description)?;
+
+            // or the comments of the version would be parsed as those of the protocol
+            if !self.version.description.is_empty() {
+                writeln!(f)?;
+            }
         }
         writeln!(f, "{}", self.version)?;
 
@@ -30,9 +40,16 @@ impl fmt::Display for Protocol<'_> {
         Ok(())
     }
 }
-impl fmt::Display for Version {
+impl fmt::Display for Version<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        writeln!(f, "version\n  major {}\n  minor {}", self.major, self.minor)
+        if !self.description.is_empty() {
+            write!(f, "{}", self.description)?;
+        }
+        writeln!(f, "version")?;
+        write!(f, "{}", indented(&self.major_description))?;
+        writeln!(f, "  major {}", self.major)?;
+        write!(f, "{}", indented(&self.minor_description))?;
+        writeln!(f, "  minor {}", self.minor)
     }
 }
 
@@ -44,18 +61,13 @@ impl fmt::Display for Domain<'_> {
 
         writeln!(
             f,
-            "{}{}domain {}",
-            if self.experimental {
-                "experimental "
-            } else {
-                ""
-            },
-            if self.deprecated { "deprecated " } else { "" },
+            "{}domain {}",
+            Modifiers(self.experimental, self.deprecated, false, &self.annotations),
             self.name,
         )?;
 
         for depends in &self.dependencies {
-            writeln!(f, "{}", indented(format!("depends on {}", depends)))?;
+            write!(f, "{}", indented(depends))?;
         }
 
         writeln!(f)?;
@@ -74,6 +86,15 @@ impl fmt::Display for Domain<'_> {
     }
 }
 
+impl fmt::Display for Dependency<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.description.is_empty() {
+            write!(f, "{}", self.description)?;
+        }
+        writeln!(f, "depends on {}", self.name)
+    }
+}
+
 impl fmt::Display for TypeDef<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         if !self.description.is_empty() {
@@ -82,13 +103,8 @@ impl fmt::Display for TypeDef<'_> {
 
         writeln!(
             f,
-            "{}{}type {} extends {}",
-            if self.experimental {
-                "experimental "
-            } else {
-                ""
-            },
-            if self.deprecated { "deprecated " } else { "" },
+            "{}type {} extends {}",
+            Modifiers(self.experimental, self.deprecated, false, &self.annotations),
             self.id,
             self.extends
         )?;
@@ -148,7 +164,31 @@ impl fmt::Display for Variant<'_> {
         if !self.description.is_empty() {
             write!(f, "{}", self.description)?;
         }
-        f.write_str(self.name)
+        f.write_str(&self.name)
+    }
+}
+
+/// The modifiers of a node, followed by a space each.
+struct Modifiers<'a>(bool, bool, bool, &'a [Cow<'a, str>]);
+
+impl fmt::Display for Modifiers<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Modifiers(experimental, deprecated, optional, annotations) = *self;
+
+        if experimental {
+            f.write_str("experimental ")?;
+        }
+        if deprecated {
+            f.write_str("deprecated ")?;
+        }
+        if optional {
+            f.write_str("optional ")?;
+        }
+        for annotation in annotations {
+            write!(f, "{} ", annotation)?;
+        }
+
+        Ok(())
     }
 }
 
@@ -174,22 +214,24 @@ impl fmt::Display for Param<'_> {
 
         writeln!(
             f,
-            "{}{}{}{} {}",
-            if self.experimental {
-                "experimental "
-            } else {
-                ""
-            },
-            if self.deprecated { "deprecated " } else { "" },
-            if self.optional { "optional " } else { "" },
+            "{}{} {}",
+            Modifiers(
+                self.experimental,
+                self.deprecated,
+                self.optional,
+                &self.annotations
+            ),
             self.ty,
             self.name
         )?;
 
-        if let Type::Enum(ref variants) = self.ty {
-            write!(f, "{}", Enum(None, variants))
-        } else {
-            Ok(())
+        match self.ty {
+            Type::Enum(ref variants) => write!(f, "{}", Enum(None, variants)),
+            Type::ArrayOf(ref ty) => match **ty {
+                Type::Enum(ref variants) => write!(f, "{}", Enum(None, variants)),
+                _ => Ok(()),
+            },
+            _ => Ok(()),
         }
     }
 }
@@ -202,13 +244,8 @@ impl fmt::Display for Command<'_> {
 
         writeln!(
             f,
-            "{}{}command {}",
-            if self.experimental {
-                "experimental "
-            } else {
-                ""
-            },
-            if self.deprecated { "deprecated " } else { "" },
+            "{}command {}",
+            Modifiers(self.experimental, self.deprecated, false, &self.annotations),
             self.name
         )?;
 
@@ -235,13 +272,8 @@ impl fmt::Display for Event<'_> {
 
         writeln!(
             f,
-            "{}{}event {}",
-            if self.experimental {
-                "experimental "
-            } else {
-                ""
-            },
-            if self.deprecated { "deprecated " } else { "" },
+            "{}event {}",
+            Modifiers(self.experimental, self.deprecated, false, &self.annotations),
             self.name
         )?;
 
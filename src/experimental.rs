@@ -0,0 +1,211 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::resolve::Owner;
+use crate::*;
+
+impl Protocol<'_> {
+    /// Check that no stable type, command or event exposes an experimental or deprecated type.
+    ///
+    /// The type references are followed transitively, skipping the experimental parameters and properties,
+    /// which aren't part of the stable API. Every experimental type reached is an error,
+    /// every deprecated one a warning unless the item is deprecated too.
+    pub fn check_experimental(&self) -> Vec<Diagnostic> {
+        let mut lint = Lint {
+            symbols: self.symbols(),
+            diagnostics: vec![],
+        };
+
+        for domain in self.domains.iter().filter(|domain| !domain.experimental) {
+            for ty in domain.types.iter().filter(|ty| !ty.experimental) {
+                let id = format!("{}.{}", domain.name, ty.id);
+
+                lint.item(
+                    format!("type `{}`", id),
+                    Some(id),
+                    ty.deprecated,
+                    ty.span,
+                    item_edges(Owner::Type(ty), domain),
+                );
+            }
+            for cmd in domain.commands.iter().filter(|cmd| !cmd.experimental) {
+                lint.item(
+                    format!("command `{}.{}`", domain.name, cmd.name),
+                    None,
+                    cmd.deprecated,
+                    cmd.span,
+                    item_edges(Owner::Command(cmd), domain),
+                );
+            }
+            for evt in domain.events.iter().filter(|evt| !evt.experimental) {
+                lint.item(
+                    format!("event `{}.{}`", domain.name, evt.name),
+                    None,
+                    evt.deprecated,
+                    evt.span,
+                    item_edges(Owner::Event(evt), domain),
+                );
+            }
+        }
+
+        lint.diagnostics
+    }
+}
+
+/// A type reference, labelled with where it is, and the domain it is used in.
+type Edge<'p> = (String, &'p str, &'p str);
+
+struct Lint<'p, 'a> {
+    symbols: SymbolTable<'p, 'a>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'p> Lint<'p, '_> {
+    /// Walk the types reachable from a stable item breadth first, so every type is reported with its shortest path.
+    ///
+    /// The `id` of a type definition is marked as visited, so it isn't reported when it refers to itself.
+    fn item(
+        &mut self,
+        item: String,
+        id: Option<String>,
+        deprecated: bool,
+        span: Span,
+        edges: Vec<Edge<'p>>,
+    ) {
+        let mut visited = id.into_iter().collect::<HashSet<_>>();
+        let mut queue = edges
+            .into_iter()
+            .map(|(label, domain, reference)| (vec![label], domain, reference))
+            .collect::<VecDeque<_>>();
+
+        while let Some((path, from, reference)) = queue.pop_front() {
+            let (target, ty) = match self.symbols.resolve(from, reference) {
+                Some(resolved) => resolved,
+                None => continue,
+            };
+            let id = format!("{}.{}", target.name, ty.id);
+
+            if !visited.insert(id.clone()) {
+                continue;
+            }
+
+            let through = path
+                .iter()
+                .chain(Some(&id))
+                .map(|label| format!("`{}`", label))
+                .collect::<Vec<_>>()
+                .join(" -> ");
+
+            if target.experimental || ty.experimental {
+                self.diagnostics.push(Diagnostic::error(
+                    "experimental-leak",
+                    format!(
+                        "stable {} exposes experimental type `{}` through {}",
+                        item, id, through
+                    ),
+                    span,
+                ));
+
+                continue;
+            }
+
+            if ty.deprecated && !deprecated {
+                self.diagnostics.push(Diagnostic::warning(
+                    "deprecated-leak",
+                    format!(
+                        "{} exposes deprecated type `{}` through {}",
+                        item, id, through
+                    ),
+                    span,
+                ));
+            }
+
+            for (label, from, reference) in item_edges(Owner::Type(ty), target) {
+                let mut path = path.clone();
+
+                path.push(label);
+                queue.push_back((path, from, reference));
+            }
+        }
+    }
+}
+
+/// The type references of an item of `domain`, skipping its experimental parameters and properties.
+fn item_edges<'p, 'a>(owner: Owner<'p, 'a>, domain: &'p Domain<'a>) -> Vec<Edge<'p>> {
+    owner
+        .references(domain)
+        .filter(|item_ref| !matches!(item_ref.site.param(), Some(param) if param.experimental))
+        .map(|item_ref| (item_ref.label(), domain.name.as_ref(), item_ref.reference))
+        .collect()
+}
+
+#[cfg(all(test, feature = "parse"))]
+mod tests {
+    #[test]
+    fn check_experimental() {
+        let protocol = crate::parse_str(
+            r#"version
+  major 1
+  minor 3
+
+experimental domain Network
+  type LoaderId extends string
+
+domain Page
+  depends on Network
+
+  type FrameId extends string
+
+  deprecated type Url extends string
+
+  experimental type AdFrameType extends string
+    enum
+      none
+      root
+
+  type Frame extends object
+    properties
+      FrameId id
+      optional Network.LoaderId loaderId
+      Url url
+      experimental optional AdFrameType adFrameType
+
+  type FrameTree extends object
+    properties
+      Frame frame
+      optional array of FrameTree childFrames
+
+  command getFrameTree
+    returns
+      FrameTree frameTree
+
+  experimental command getAdFrameType
+    returns
+      AdFrameType adFrameType
+
+  deprecated event frameNavigated
+    parameters
+      Frame frame
+      AdFrameType type
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            protocol
+                .check_experimental()
+                .iter()
+                .map(|diag| diag.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "20:3: error[experimental-leak]: stable type `Page.Frame` exposes experimental type `Network.LoaderId` through `Page.Frame.loaderId` -> `Network.LoaderId`",
+                "20:3: warning[deprecated-leak]: type `Page.Frame` exposes deprecated type `Page.Url` through `Page.Frame.url` -> `Page.Url`",
+                "27:3: error[experimental-leak]: stable type `Page.FrameTree` exposes experimental type `Network.LoaderId` through `Page.FrameTree.frame` -> `Page.Frame.loaderId` -> `Network.LoaderId`",
+                "27:3: warning[deprecated-leak]: type `Page.FrameTree` exposes deprecated type `Page.Url` through `Page.FrameTree.frame` -> `Page.Frame.url` -> `Page.Url`",
+                "32:3: error[experimental-leak]: stable command `Page.getFrameTree` exposes experimental type `Network.LoaderId` through `Page.getFrameTree.frameTree` -> `Page.FrameTree.frame` -> `Page.Frame.loaderId` -> `Network.LoaderId`",
+                "32:3: warning[deprecated-leak]: command `Page.getFrameTree` exposes deprecated type `Page.Url` through `Page.getFrameTree.frameTree` -> `Page.FrameTree.frame` -> `Page.Frame.url` -> `Page.Url`",
+                "40:3: error[experimental-leak]: stable event `Page.frameNavigated` exposes experimental type `Page.AdFrameType` through `Page.frameNavigated.type` -> `Page.AdFrameType`",
+                "40:3: error[experimental-leak]: stable event `Page.frameNavigated` exposes experimental type `Network.LoaderId` through `Page.frameNavigated.frame` -> `Page.Frame.loaderId` -> `Network.LoaderId`",
+            ]
+        );
+    }
+}
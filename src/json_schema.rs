@@ -0,0 +1,331 @@
+use serde_json::{json, Map, Value};
+
+use crate::*;
+
+/// The dialect of the generated schemas.
+pub const JSON_SCHEMA_DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+impl Protocol<'_> {
+    /// Turn the `Protocol` into a JSON Schema (draft 2020-12) document.
+    ///
+    /// Every schema is in `$defs`, named after what it describes:
+    ///
+    /// - `Domain.Type` for a type definition,
+    /// - `Domain.command.parameters` for a command, and `Domain.command.returns` if it returns anything,
+    /// - `Domain.event.event` for the parameters of an event.
+    pub fn to_json_schema(&self) -> Value {
+        json!({
+            "$schema": JSON_SCHEMA_DIALECT,
+            "$comment": format!("version {}.{}", self.version.major, self.version.minor),
+            "$defs": Dialect::JSON_SCHEMA.schemas(self),
+        })
+    }
+}
+
+/// Where the schemas are put and what they keep, for the documents embedding them.
+pub(crate) struct Dialect {
+    /// The prefix of a reference to a schema by its name.
+    refs: &'static str,
+    /// The keyword to keep the `experimental` flags as, if any.
+    experimental: Option<&'static str>,
+    /// The keyword to keep the `deprecated` flags as.
+    deprecated: &'static str,
+}
+
+impl Dialect {
+    pub const JSON_SCHEMA: Dialect = Dialect {
+        refs: "#/$defs/",
+        experimental: None,
+        deprecated: "deprecated",
+    };
+
+    #[cfg(feature = "asyncapi")]
+    pub const ASYNCAPI: Dialect = Dialect {
+        refs: "#/components/schemas/",
+        experimental: Some("x-experimental"),
+        deprecated: "deprecated",
+    };
+
+    /// The AsyncAPI objects other than schemas, like channels and operations, which have no `deprecated` keyword.
+    #[cfg(feature = "asyncapi")]
+    pub const ASYNCAPI_OBJECTS: Dialect = Dialect {
+        deprecated: "x-deprecated",
+        ..Dialect::ASYNCAPI
+    };
+
+    /// The name of the schema of `name` defined in `domain`.
+    pub fn name(&self, domain: &Domain, name: &str) -> String {
+        format!("{}.{}", domain.name, name)
+    }
+
+    /// The name of the schema of the event `name` defined in `domain`, apart from the types.
+    pub fn event(&self, domain: &Domain, name: &str) -> String {
+        format!("{}.event", self.name(domain, name))
+    }
+
+    /// A reference to the schema named `name`.
+    pub fn reference(&self, name: &str) -> Value {
+        json!({ "$ref": format!("{}{}", self.refs, name) })
+    }
+
+    /// The schemas of every type, command and event, by their names.
+    pub fn schemas(&self, protocol: &Protocol) -> Map<String, Value> {
+        let mut schemas = Map::new();
+
+        for domain in &protocol.domains {
+            for ty in &domain.types {
+                schemas.insert(self.name(domain, &ty.id), self.type_def(domain, ty));
+            }
+
+            for cmd in &domain.commands {
+                let name = self.name(domain, &cmd.name);
+
+                schemas.insert(
+                    format!("{}.parameters", name),
+                    self.command(domain, cmd, &cmd.parameters),
+                );
+                if !cmd.returns.is_empty() {
+                    schemas.insert(
+                        format!("{}.returns", name),
+                        self.command(domain, cmd, &cmd.returns),
+                    );
+                }
+            }
+
+            for evt in &domain.events {
+                let mut schema = self.object(domain, &evt.parameters);
+
+                self.annotate(
+                    &mut schema,
+                    &evt.description,
+                    evt.experimental,
+                    evt.deprecated,
+                );
+                schemas.insert(self.event(domain, &evt.name), schema);
+            }
+        }
+
+        schemas
+    }
+
+    fn type_def(&self, domain: &Domain, ty: &TypeDef) -> Value {
+        let mut schema = match (&ty.extends, &ty.item) {
+            (Type::ArrayOf(_), Some(Item::Enum(variants))) => {
+                json!({ "type": "array", "items": enum_schema(variants) })
+            }
+            (_, Some(Item::Enum(variants))) => enum_schema(variants),
+            (_, Some(Item::Properties(props))) => self.object(domain, props),
+            (_, None) => self.schema(domain, &ty.extends),
+        };
+
+        self.annotate(&mut schema, &ty.description, ty.experimental, ty.deprecated);
+
+        schema
+    }
+
+    fn command(&self, domain: &Domain, cmd: &Command, params: &[Param]) -> Value {
+        let mut schema = self.object(domain, params);
+
+        self.annotate(
+            &mut schema,
+            &cmd.description,
+            cmd.experimental,
+            cmd.deprecated,
+        );
+
+        schema
+    }
+
+    /// The schema of an object with the `params` as its properties.
+    fn object(&self, domain: &Domain, params: &[Param]) -> Value {
+        let properties = params
+            .iter()
+            .map(|param| {
+                let mut schema = self.schema(domain, &param.ty);
+
+                self.annotate(
+                    &mut schema,
+                    &param.description,
+                    param.experimental,
+                    param.deprecated,
+                );
+
+                (param.name.to_string(), schema)
+            })
+            .collect::<Map<_, _>>();
+        let required = params
+            .iter()
+            .filter(|param| !param.optional)
+            .map(|param| param.name.as_ref())
+            .collect::<Vec<_>>();
+
+        let mut schema = json!({
+            "type": "object",
+            "properties": properties,
+        });
+
+        if !required.is_empty() {
+            schema["required"] = json!(required);
+        }
+
+        schema
+    }
+
+    fn schema(&self, domain: &Domain, ty: &Type) -> Value {
+        match ty {
+            Type::Integer => json!({ "type": "integer" }),
+            Type::Number => json!({ "type": "number" }),
+            Type::Boolean => json!({ "type": "boolean" }),
+            Type::String => json!({ "type": "string" }),
+            Type::Object => json!({ "type": "object" }),
+            Type::Any => json!({}),
+            Type::Binary => json!({ "type": "string", "contentEncoding": "base64" }),
+            Type::Enum(variants) => enum_schema(variants),
+            Type::ArrayOf(ty) => json!({ "type": "array", "items": self.schema(domain, ty) }),
+            Type::Ref(id) if id.contains('.') => self.reference(id),
+            Type::Ref(id) => self.reference(&self.name(domain, id)),
+        }
+    }
+
+    pub fn annotate(
+        &self,
+        value: &mut Value,
+        description: &Description,
+        experimental: bool,
+        deprecated: bool,
+    ) {
+        if !description.is_empty() {
+            value["description"] = description.joined().into();
+        }
+        if let (true, Some(keyword)) = (experimental, self.experimental) {
+            value[keyword] = true.into();
+        }
+        if deprecated {
+            value[self.deprecated] = true.into();
+        }
+    }
+}
+
+fn enum_schema(variants: &[Variant]) -> Value {
+    json!({
+        "type": "string",
+        "enum": variants.iter().map(|variant| variant.name.as_ref()).collect::<Vec<_>>(),
+    })
+}
+
+#[cfg(all(test, feature = "parse"))]
+mod tests {
+    use super::*;
+
+    const PDL: &str = r#"version
+  major 1
+  minor 3
+
+domain Page
+  depends on Network
+
+  # Unique frame identifier.
+  type FrameId extends string
+
+  type TransitionType extends string
+    enum
+      link
+      typed
+
+  type TransitionTypes extends array of enum
+    enum
+      link
+      typed
+
+  type Frame extends object
+    properties
+      FrameId id
+      optional Network.LoaderId loaderId
+      deprecated optional array of string urls
+
+  # Capture page screenshot.
+  deprecated command captureScreenshot
+    parameters
+      optional enum format
+        jpeg
+        png
+    returns
+      # Base64-encoded image data.
+      binary data
+
+  command bringToFront
+
+  event loadEventFired
+    parameters
+      any timestamp
+"#;
+
+    #[test]
+    fn json_schema() {
+        let schema = crate::parse_str(PDL).unwrap().to_json_schema();
+
+        assert_eq!(schema["$schema"], JSON_SCHEMA_DIALECT);
+        assert_eq!(
+            schema["$defs"],
+            json!({
+                "Page.FrameId": {
+                    "description": "Unique frame identifier.",
+                    "type": "string"
+                },
+                "Page.TransitionType": {
+                    "type": "string",
+                    "enum": ["link", "typed"]
+                },
+                "Page.TransitionTypes": {
+                    "type": "array",
+                    "items": { "type": "string", "enum": ["link", "typed"] }
+                },
+                "Page.Frame": {
+                    "type": "object",
+                    "properties": {
+                        "id": { "$ref": "#/$defs/Page.FrameId" },
+                        "loaderId": { "$ref": "#/$defs/Network.LoaderId" },
+                        "urls": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "deprecated": true
+                        }
+                    },
+                    "required": ["id"]
+                },
+                "Page.captureScreenshot.parameters": {
+                    "description": "Capture page screenshot.",
+                    "deprecated": true,
+                    "type": "object",
+                    "properties": {
+                        "format": { "type": "string", "enum": ["jpeg", "png"] }
+                    }
+                },
+                "Page.captureScreenshot.returns": {
+                    "description": "Capture page screenshot.",
+                    "deprecated": true,
+                    "type": "object",
+                    "properties": {
+                        "data": {
+                            "description": "Base64-encoded image data.",
+                            "type": "string",
+                            "contentEncoding": "base64"
+                        }
+                    },
+                    "required": ["data"]
+                },
+                "Page.bringToFront.parameters": {
+                    "type": "object",
+                    "properties": {}
+                },
+                "Page.loadEventFired.event": {
+                    "type": "object",
+                    "properties": {
+                        "timestamp": {}
+                    },
+                    "required": ["timestamp"]
+                }
+            })
+        );
+    }
+}
@@ -1,4 +1,5 @@
-use std::ops::Deref;
+use std::borrow::Cow;
+use std::ops::{Deref, Range};
 
 use cfg_if::cfg_if;
 
@@ -9,13 +10,29 @@ cfg_if! {
 
         mod parse;
 
-        pub use parse::parse;
+        pub use parse::{
+            parse, parse_file, parse_reader, parse_recovering, parse_str, Descendants, Document,
+            DomainIter, FileLoader, IncludedFrom, LoadError, Loader, MemoryLoader, NodeKind,
+            ParseError, Sources, SyntaxElement, SyntaxNode, SyntaxTree, TextEdit, Token, TokenKind,
+        };
     }
 }
 
 #[cfg(feature = "display")]
 mod display;
 
+mod depends;
+mod diagnostic;
+mod experimental;
+mod owned;
+mod recursive;
+mod resolve;
+mod validate;
+
+pub use diagnostic::{Diagnostic, Severity};
+pub use recursive::RecursiveTypes;
+pub use resolve::SymbolTable;
+
 cfg_if! {
     if #[cfg(feature = "to_json")] {
         use serde::Serialize;
@@ -24,12 +41,39 @@ cfg_if! {
     }
 }
 
+#[cfg(feature = "from_json")]
+mod de;
+
+cfg_if! {
+    if #[cfg(feature = "json_schema")] {
+        mod json_schema;
+
+        pub use json_schema::JSON_SCHEMA_DIALECT;
+    }
+}
+
+cfg_if! {
+    if #[cfg(feature = "asyncapi")] {
+        mod asyncapi;
+
+        pub use asyncapi::ASYNCAPI_VERSION;
+    }
+}
+
+cfg_if! {
+    if #[cfg(feature = "proto")] {
+        mod proto;
+
+        pub use proto::ProtoFile;
+    }
+}
+
 #[cfg_attr(feature = "to_json", derive(Serialize))]
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
-pub struct Description<'a>(Vec<&'a str>);
+pub struct Description<'a>(Vec<Cow<'a, str>>);
 
 impl<'a> Deref for Description<'a> {
-    type Target = [&'a str];
+    type Target = [Cow<'a, str>];
 
     fn deref(&self) -> &Self::Target {
         self.0.as_slice()
@@ -38,43 +82,199 @@ impl<'a> Deref for Description<'a> {
 
 impl<'a> From<Vec<&'a str>> for Description<'a> {
     fn from(comments: Vec<&'a str>) -> Description<'a> {
+        Description(comments.into_iter().map(Cow::Borrowed).collect())
+    }
+}
+
+impl<'a> From<Vec<Cow<'a, str>>> for Description<'a> {
+    fn from(comments: Vec<Cow<'a, str>>) -> Description<'a> {
         Description(comments)
     }
 }
 
 impl<'a> From<&'a str> for Description<'a> {
     fn from(comment: &'a str) -> Description<'a> {
-        Description(vec![comment])
+        Description(vec![Cow::Borrowed(comment)])
     }
 }
 
-impl Description<'_> {
+impl<'a> Description<'a> {
     pub fn is_empty(&self) -> bool {
         self.0.is_empty()
     }
+
+    /// The text of the description with its lines joined by newlines, like Chromium's JSON converter.
+    pub fn joined(&self) -> String {
+        self.0.join("\n")
+    }
+
+    /// The paragraphs of the description, separated by blank comment lines.
+    pub fn paragraphs(&self) -> impl Iterator<Item = &[Cow<'a, str>]> {
+        self.0
+            .split(|line| line.trim().is_empty())
+            .filter(|paragraph| !paragraph.is_empty())
+    }
+
+    /// The description as Markdown, keeping the lines of each paragraph and separating paragraphs
+    /// with a single blank line.
+    pub fn to_markdown(&self) -> String {
+        self.paragraphs()
+            .map(|paragraph| paragraph.join("\n"))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+/// The location of a node in the PDL source.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    /// The byte offset of the start of the node.
+    pub start: usize,
+    /// The byte offset of the end of the node.
+    pub end: usize,
+    /// The 1-based line number of the start of the node.
+    pub line: usize,
+    /// The 1-based column number of the start of the node, counted in characters.
+    pub column: usize,
+}
+
+impl Span {
+    /// The byte range of the node.
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
 }
 
+/// A node which knows its location in the PDL source.
+pub trait Spanned {
+    fn span(&self) -> Span;
+}
+
+macro_rules! spanned {
+    ($($node:ident),*) => {
+        $(
+            impl Spanned for $node<'_> {
+                fn span(&self) -> Span {
+                    self.span
+                }
+            }
+        )*
+    };
+}
+
+spanned!(Domain, Dependency, TypeDef, Variant, Param, Command, Event, Redirect);
+
+/// Compare the nodes field by field except for their span,
+/// so two nodes are equal when their content is, wherever they come from.
+macro_rules! eq_without_span {
+    ($($node:ident { $($field:ident),* }),*) => {
+        $(
+            impl PartialEq for $node<'_> {
+                fn eq(&self, other: &Self) -> bool {
+                    let $node { $($field,)* span: _ } = self;
+
+                    $(*$field == other.$field)&&*
+                }
+            }
+
+            impl Eq for $node<'_> {}
+        )*
+    };
+}
+
+eq_without_span!(
+    Domain {
+        description,
+        experimental,
+        deprecated,
+        annotations,
+        name,
+        dependencies,
+        types,
+        commands,
+        events
+    },
+    Dependency { description, name },
+    TypeDef {
+        description,
+        experimental,
+        deprecated,
+        annotations,
+        id,
+        extends,
+        item
+    },
+    Variant { description, name },
+    Param {
+        description,
+        experimental,
+        deprecated,
+        optional,
+        annotations,
+        ty,
+        name
+    },
+    Command {
+        description,
+        experimental,
+        deprecated,
+        annotations,
+        name,
+        redirect,
+        parameters,
+        returns
+    },
+    Event {
+        description,
+        experimental,
+        deprecated,
+        annotations,
+        name,
+        parameters
+    },
+    Redirect { description, to }
+);
+
 #[cfg_attr(feature = "to_json", derive(Serialize))]
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Protocol<'a> {
     #[cfg_attr(feature = "to_json", serde(skip_serializing))]
     pub description: Description<'a>,
-    pub version: Version,
+    pub version: Version<'a>,
     #[cfg_attr(feature = "to_json", serde(skip_serializing_if = "Vec::is_empty"))]
     pub domains: Vec<Domain<'a>>,
 }
 
 #[cfg_attr(feature = "to_json", derive(Serialize))]
-#[derive(Clone, Debug, PartialEq, Eq)]
-pub struct Version {
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Version<'a> {
     #[cfg_attr(feature = "to_json", serde(serialize_with = "ser::serialize_usize"))]
     pub major: usize,
     #[cfg_attr(feature = "to_json", serde(serialize_with = "ser::serialize_usize"))]
     pub minor: usize,
+    /// The comments above `version`.
+    #[cfg_attr(feature = "to_json", serde(skip))]
+    pub description: Description<'a>,
+    /// The comments above `major`.
+    #[cfg_attr(feature = "to_json", serde(skip))]
+    pub major_description: Description<'a>,
+    /// The comments above `minor`.
+    #[cfg_attr(feature = "to_json", serde(skip))]
+    pub minor_description: Description<'a>,
+}
+
+impl Version<'_> {
+    pub fn new(major: usize, minor: usize) -> Self {
+        Version {
+            major,
+            minor,
+            ..Default::default()
+        }
+    }
 }
 
 #[cfg_attr(feature = "to_json", derive(Serialize))]
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug)]
 pub struct Domain<'a> {
     #[cfg_attr(
         feature = "to_json",
@@ -89,20 +289,49 @@ pub struct Domain<'a> {
     pub experimental: bool,
     #[cfg_attr(feature = "to_json", serde(skip_serializing_if = "ser::is_false"))]
     pub deprecated: bool,
+    /// The modifiers which aren't known to PDL, e.g. vendor extensions.
+    #[cfg_attr(feature = "to_json", serde(skip_serializing_if = "Vec::is_empty"))]
+    pub annotations: Vec<Cow<'a, str>>,
     #[cfg_attr(feature = "to_json", serde(rename = "domain"))]
-    pub name: &'a str,
+    pub name: Cow<'a, str>,
     #[cfg_attr(feature = "to_json", serde(skip_serializing_if = "Vec::is_empty"))]
-    pub dependencies: Vec<&'a str>,
+    pub dependencies: Vec<Dependency<'a>>,
     #[cfg_attr(feature = "to_json", serde(skip_serializing_if = "Vec::is_empty"))]
     pub types: Vec<TypeDef<'a>>,
     #[cfg_attr(feature = "to_json", serde(skip_serializing_if = "Vec::is_empty"))]
     pub commands: Vec<Command<'a>>,
     #[cfg_attr(feature = "to_json", serde(skip_serializing_if = "Vec::is_empty"))]
     pub events: Vec<Event<'a>>,
+    #[cfg_attr(feature = "to_json", serde(skip))]
+    pub span: Span,
+}
+
+/// A `depends on` line of a domain, serialized as the name of the domain.
+#[derive(Clone, Debug)]
+pub struct Dependency<'a> {
+    pub description: Description<'a>,
+    pub name: Cow<'a, str>,
+    pub span: Span,
+}
+
+impl<'a> Dependency<'a> {
+    pub fn new<S: Into<Cow<'a, str>>>(name: S) -> Dependency<'a> {
+        Dependency {
+            description: Default::default(),
+            name: name.into(),
+            span: Default::default(),
+        }
+    }
+}
+
+impl<'a> From<&'a str> for Dependency<'a> {
+    fn from(name: &'a str) -> Dependency<'a> {
+        Dependency::new(name)
+    }
 }
 
 #[cfg_attr(feature = "to_json", derive(Serialize))]
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug)]
 pub struct TypeDef<'a> {
     #[cfg_attr(
         feature = "to_json",
@@ -117,11 +346,16 @@ pub struct TypeDef<'a> {
     pub experimental: bool,
     #[cfg_attr(feature = "to_json", serde(skip_serializing_if = "ser::is_false"))]
     pub deprecated: bool,
-    pub id: &'a str,
+    /// The modifiers which aren't known to PDL, e.g. vendor extensions.
+    #[cfg_attr(feature = "to_json", serde(skip_serializing_if = "Vec::is_empty"))]
+    pub annotations: Vec<Cow<'a, str>>,
+    pub id: Cow<'a, str>,
     #[cfg_attr(feature = "to_json", serde(flatten))]
     pub extends: Type<'a>,
     #[cfg_attr(feature = "to_json", serde(flatten))]
     pub item: Option<Item<'a>>,
+    #[cfg_attr(feature = "to_json", serde(skip))]
+    pub span: Span,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -135,7 +369,7 @@ pub enum Type<'a> {
     Binary,
     Enum(Vec<Variant<'a>>),
     ArrayOf(Box<Type<'a>>),
-    Ref(&'a str),
+    Ref(Cow<'a, str>),
 }
 
 #[cfg_attr(feature = "to_json", derive(Serialize))]
@@ -148,7 +382,7 @@ pub enum Item<'a> {
 }
 
 #[cfg_attr(feature = "to_json", derive(Serialize))]
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug)]
 pub struct Variant<'a> {
     #[cfg_attr(
         feature = "to_json",
@@ -159,20 +393,23 @@ pub struct Variant<'a> {
         serde(serialize_with = "ser::serialize_description")
     )]
     pub description: Description<'a>,
-    pub name: &'a str,
+    pub name: Cow<'a, str>,
+    #[cfg_attr(feature = "to_json", serde(skip))]
+    pub span: Span,
 }
 
 impl<'a> Variant<'a> {
-    pub fn new(name: &str) -> Variant {
+    pub fn new<S: Into<Cow<'a, str>>>(name: S) -> Variant<'a> {
         Variant {
             description: Default::default(),
-            name,
+            name: name.into(),
+            span: Default::default(),
         }
     }
 }
 
 #[cfg_attr(feature = "to_json", derive(Serialize))]
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug)]
 pub struct Param<'a> {
     #[cfg_attr(
         feature = "to_json",
@@ -189,13 +426,18 @@ pub struct Param<'a> {
     pub deprecated: bool,
     #[cfg_attr(feature = "to_json", serde(skip_serializing_if = "ser::is_false"))]
     pub optional: bool,
+    /// The modifiers which aren't known to PDL, e.g. vendor extensions.
+    #[cfg_attr(feature = "to_json", serde(skip_serializing_if = "Vec::is_empty"))]
+    pub annotations: Vec<Cow<'a, str>>,
     #[cfg_attr(feature = "to_json", serde(flatten))]
     pub ty: Type<'a>,
-    pub name: &'a str,
+    pub name: Cow<'a, str>,
+    #[cfg_attr(feature = "to_json", serde(skip))]
+    pub span: Span,
 }
 
 #[cfg_attr(feature = "to_json", derive(Serialize))]
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug)]
 pub struct Command<'a> {
     #[cfg_attr(
         feature = "to_json",
@@ -210,18 +452,22 @@ pub struct Command<'a> {
     pub experimental: bool,
     #[cfg_attr(feature = "to_json", serde(skip_serializing_if = "ser::is_false"))]
     pub deprecated: bool,
-    pub name: &'a str,
-    #[cfg_attr(feature = "to_json", serde(skip_serializing_if = "Option::is_none"))]
-    #[cfg_attr(feature = "to_json", serde(serialize_with = "ser::serialize_redirect"))]
+    /// The modifiers which aren't known to PDL, e.g. vendor extensions.
+    #[cfg_attr(feature = "to_json", serde(skip_serializing_if = "Vec::is_empty"))]
+    pub annotations: Vec<Cow<'a, str>>,
+    pub name: Cow<'a, str>,
+    #[cfg_attr(feature = "to_json", serde(flatten))]
     pub redirect: Option<Redirect<'a>>,
     #[cfg_attr(feature = "to_json", serde(skip_serializing_if = "Vec::is_empty"))]
     pub parameters: Vec<Param<'a>>,
     #[cfg_attr(feature = "to_json", serde(skip_serializing_if = "Vec::is_empty"))]
     pub returns: Vec<Param<'a>>,
+    #[cfg_attr(feature = "to_json", serde(skip))]
+    pub span: Span,
 }
 
 #[cfg_attr(feature = "to_json", derive(Serialize))]
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug)]
 pub struct Event<'a> {
     #[cfg_attr(
         feature = "to_json",
@@ -236,13 +482,20 @@ pub struct Event<'a> {
     pub experimental: bool,
     #[cfg_attr(feature = "to_json", serde(skip_serializing_if = "ser::is_false"))]
     pub deprecated: bool,
-    pub name: &'a str,
+    /// The modifiers which aren't known to PDL, e.g. vendor extensions.
+    #[cfg_attr(feature = "to_json", serde(skip_serializing_if = "Vec::is_empty"))]
+    pub annotations: Vec<Cow<'a, str>>,
+    pub name: Cow<'a, str>,
     #[cfg_attr(feature = "to_json", serde(skip_serializing_if = "Vec::is_empty"))]
     pub parameters: Vec<Param<'a>>,
+    #[cfg_attr(feature = "to_json", serde(skip))]
+    pub span: Span,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+/// Where a command moved to, serialized as the `redirect` of the command.
+#[derive(Clone, Debug)]
 pub struct Redirect<'a> {
     pub description: Description<'a>,
-    pub to: &'a str,
+    pub to: Cow<'a, str>,
+    pub span: Span,
 }
@@ -0,0 +1,180 @@
+use std::borrow::Cow;
+
+use crate::*;
+
+fn owned(s: Cow<'_, str>) -> Cow<'static, str> {
+    Cow::Owned(s.into_owned())
+}
+
+impl Description<'_> {
+    /// Take ownership of the comments, detaching them from the PDL source.
+    pub fn into_owned(self) -> Description<'static> {
+        Description(self.0.into_iter().map(owned).collect())
+    }
+}
+
+impl Protocol<'_> {
+    /// Take ownership of the whole `Protocol`, so it can outlive the PDL source.
+    pub fn into_owned(self) -> Protocol<'static> {
+        Protocol {
+            description: self.description.into_owned(),
+            version: self.version.into_owned(),
+            domains: self.domains.into_iter().map(Domain::into_owned).collect(),
+        }
+    }
+}
+
+impl Version<'_> {
+    pub fn into_owned(self) -> Version<'static> {
+        Version {
+            major: self.major,
+            minor: self.minor,
+            description: self.description.into_owned(),
+            major_description: self.major_description.into_owned(),
+            minor_description: self.minor_description.into_owned(),
+        }
+    }
+}
+
+impl Domain<'_> {
+    pub fn into_owned(self) -> Domain<'static> {
+        Domain {
+            description: self.description.into_owned(),
+            experimental: self.experimental,
+            deprecated: self.deprecated,
+            annotations: self.annotations.into_iter().map(owned).collect(),
+            name: owned(self.name),
+            dependencies: self
+                .dependencies
+                .into_iter()
+                .map(Dependency::into_owned)
+                .collect(),
+            types: self.types.into_iter().map(TypeDef::into_owned).collect(),
+            commands: self.commands.into_iter().map(Command::into_owned).collect(),
+            events: self.events.into_iter().map(Event::into_owned).collect(),
+            span: self.span,
+        }
+    }
+}
+
+impl Dependency<'_> {
+    pub fn into_owned(self) -> Dependency<'static> {
+        Dependency {
+            description: self.description.into_owned(),
+            name: owned(self.name),
+            span: self.span,
+        }
+    }
+}
+
+impl TypeDef<'_> {
+    pub fn into_owned(self) -> TypeDef<'static> {
+        TypeDef {
+            description: self.description.into_owned(),
+            experimental: self.experimental,
+            deprecated: self.deprecated,
+            annotations: self.annotations.into_iter().map(owned).collect(),
+            id: owned(self.id),
+            extends: self.extends.into_owned(),
+            item: self.item.map(Item::into_owned),
+            span: self.span,
+        }
+    }
+}
+
+impl Type<'_> {
+    pub fn into_owned(self) -> Type<'static> {
+        match self {
+            Type::Integer => Type::Integer,
+            Type::Number => Type::Number,
+            Type::Boolean => Type::Boolean,
+            Type::String => Type::String,
+            Type::Object => Type::Object,
+            Type::Any => Type::Any,
+            Type::Binary => Type::Binary,
+            Type::Enum(variants) => {
+                Type::Enum(variants.into_iter().map(Variant::into_owned).collect())
+            }
+            Type::ArrayOf(ty) => Type::ArrayOf(Box::new(ty.into_owned())),
+            Type::Ref(id) => Type::Ref(owned(id)),
+        }
+    }
+}
+
+impl Item<'_> {
+    pub fn into_owned(self) -> Item<'static> {
+        match self {
+            Item::Enum(variants) => {
+                Item::Enum(variants.into_iter().map(Variant::into_owned).collect())
+            }
+            Item::Properties(props) => {
+                Item::Properties(props.into_iter().map(Param::into_owned).collect())
+            }
+        }
+    }
+}
+
+impl Variant<'_> {
+    pub fn into_owned(self) -> Variant<'static> {
+        Variant {
+            description: self.description.into_owned(),
+            name: owned(self.name),
+            span: self.span,
+        }
+    }
+}
+
+impl Param<'_> {
+    pub fn into_owned(self) -> Param<'static> {
+        Param {
+            description: self.description.into_owned(),
+            experimental: self.experimental,
+            deprecated: self.deprecated,
+            annotations: self.annotations.into_iter().map(owned).collect(),
+            optional: self.optional,
+            ty: self.ty.into_owned(),
+            name: owned(self.name),
+            span: self.span,
+        }
+    }
+}
+
+impl Command<'_> {
+    pub fn into_owned(self) -> Command<'static> {
+        Command {
+            description: self.description.into_owned(),
+            experimental: self.experimental,
+            deprecated: self.deprecated,
+            annotations: self.annotations.into_iter().map(owned).collect(),
+            name: owned(self.name),
+            redirect: self.redirect.map(Redirect::into_owned),
+            parameters: self.parameters.into_iter().map(Param::into_owned).collect(),
+            returns: self.returns.into_iter().map(Param::into_owned).collect(),
+            span: self.span,
+        }
+    }
+}
+
+impl Event<'_> {
+    pub fn into_owned(self) -> Event<'static> {
+        Event {
+            description: self.description.into_owned(),
+            experimental: self.experimental,
+            deprecated: self.deprecated,
+            annotations: self.annotations.into_iter().map(owned).collect(),
+            name: owned(self.name),
+            parameters: self.parameters.into_iter().map(Param::into_owned).collect(),
+            span: self.span,
+        }
+    }
+}
+
+impl Redirect<'_> {
+    pub fn into_owned(self) -> Redirect<'static> {
+        Redirect {
+            description: self.description.into_owned(),
+            to: owned(self.to),
+            span: self.span,
+        }
+    }
+}
@@ -1,215 +1,442 @@
-use std::ops::RangeFrom;
+use std::borrow::Cow;
+use std::convert::TryFrom;
 use std::str::FromStr;
 
 use nom::{
     branch::alt,
-    bytes::complete::{is_a, tag, take_until, take_while},
-    character::complete::char,
+    bytes::complete::{is_a, tag, take_while},
+    character::complete::{char, line_ending, not_line_ending, space0},
     combinator::{map, map_res, opt, recognize, verify},
-    error::ParseError,
+    error::context,
     multi::{many0, many1},
     sequence::{pair, preceded, tuple},
-    AsChar, Compare, IResult, InputIter, InputLength, InputTake, InputTakeAtPosition, Slice,
 };
 
 use crate::*;
 
+mod cst;
+mod error;
+mod include;
+mod incremental;
+mod iter;
+mod locate;
+mod recover;
+
+use self::error::{Error, Expected};
+use self::locate::{LineIndex, Locate};
+
+pub use self::cst::{
+    Descendants, NodeKind, SyntaxElement, SyntaxNode, SyntaxTree, Token, TokenKind,
+};
+pub use self::error::{IncludedFrom, ParseError};
+pub use self::include::{
+    parse_file, parse_reader, FileLoader, LoadError, Loader, MemoryLoader, Sources,
+};
+pub use self::incremental::{Document, TextEdit};
+pub use self::iter::DomainIter;
+pub use self::recover::parse_recovering;
+
+type IResult<'a, O> = nom::IResult<&'a str, O, Error<'a>>;
+
 /// Parse a `Protocol` from a string of PDL format.
-pub fn parse(input: &str) -> IResult<&str, Protocol> {
-    protocol(input)
+///
+/// Returns the unparsed remainder of the input together with the `Protocol`.
+pub fn parse(input: &str) -> Result<(&str, Protocol<'_>), ParseError> {
+    let (rest, mut protocol) = protocol(input).map_err(|err| ParseError::from_nom(input, err))?;
+
+    protocol.locate(&LineIndex::new(input));
+
+    Ok((rest, protocol))
+}
+
+/// Parse a `Protocol` from a string of PDL format, requiring the whole input to be consumed.
+///
+/// Any input left over is reported as an error at the first line that could not be parsed.
+pub fn parse_str(input: &str) -> Result<Protocol<'_>, ParseError> {
+    let (rest, protocol) = parse(input)?;
+    let (rest, _) = empty_lines(rest).map_err(|err| ParseError::from_nom(input, err))?;
+
+    if rest.is_empty() {
+        Ok(protocol)
+    } else {
+        Err(ParseError::new(input, unparsed(rest)))
+    }
+}
+
+impl<'a> TryFrom<&'a str> for Protocol<'a> {
+    type Error = ParseError;
+
+    fn try_from(input: &'a str) -> Result<Self, Self::Error> {
+        parse_str(input)
+    }
+}
+
+impl FromStr for Protocol<'static> {
+    type Err = ParseError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        parse_str(input).map(Protocol::into_owned)
+    }
+}
+
+fn protocol(input: &str) -> IResult<'_, Protocol<'_>> {
+    context(
+        "protocol",
+        map(
+            tuple((
+                bom,
+                description,
+                empty_lines,
+                version,
+                many1(preceded(empty_lines, domain)),
+            )),
+            |(_bom, description, _, version, domains)| Protocol {
+                description,
+                version,
+                domains,
+            },
+        ),
+    )(input)
+}
+
+/// A top level entry of a PDL file which may `include` other files.
+enum Entry<'a> {
+    Domain(Domain<'a>),
+    Include(&'a str),
+}
+
+fn root_file(input: &str) -> IResult<'_, (Description<'_>, Version<'_>, Vec<Entry<'_>>)> {
+    context(
+        "protocol",
+        map(
+            tuple((
+                bom,
+                description,
+                empty_lines,
+                version,
+                many0(preceded(empty_lines, entry)),
+                empty_lines,
+            )),
+            |(_bom, description, _, version, entries, _)| (description, version, entries),
+        ),
+    )(input)
 }
 
-fn protocol(input: &str) -> IResult<&str, Protocol> {
+fn included_file(input: &str) -> IResult<'_, Vec<Entry<'_>>> {
     map(
         tuple((
-            description,
+            bom,
+            opt(pair(many1(comment), many1(eol))),
+            many0(preceded(empty_lines, entry)),
             empty_lines,
-            version,
-            many1(preceded(empty_lines, domain)),
         )),
-        |(description, _, version, domains)| Protocol {
-            description,
-            version,
-            domains,
-        },
+        |(_bom, _header, entries, _)| entries,
     )(input)
 }
 
-fn indent(input: &str) -> IResult<&str, &str> {
-    recognize(many1(is_a(" \t")))(input)
+fn entry(input: &str) -> IResult<'_, Entry<'_>> {
+    alt((map(include, Entry::Include), map(domain, Entry::Domain)))(input)
+}
+
+fn include(input: &str) -> IResult<'_, &str> {
+    context(
+        "include",
+        map(
+            tuple((
+                keyword("include"),
+                char(' '),
+                expect("a path", verify(rest_of_line, |s: &str| !s.is_empty())),
+                eol,
+            )),
+            |(_include, _, path, _eol)| path,
+        ),
+    )(input)
 }
 
-fn empty_lines(input: &str) -> IResult<&str, &str> {
+/// Explain why the grammar stopped before consuming `rest`.
+fn unparsed(rest: &str) -> Error<'_> {
+    let any_entry = alt((map(entry, |_| ()), map(domain_item, |_| ())));
+
+    match preceded(empty_lines, any_entry)(rest) {
+        Err(nom::Err::Error(err)) | Err(nom::Err::Failure(err)) => err,
+        _ => Error::new(rest),
+    }
+}
+
+fn indent(input: &str) -> IResult<'_, &str> {
+    expect("indentation", recognize(many1(is_a(" \t"))))(input)
+}
+
+fn empty_lines(input: &str) -> IResult<'_, &str> {
     recognize(many0(eol))(input)
 }
 
-fn eol(input: &str) -> IResult<&str, char> {
-    char('\n')(input)
+fn eol(input: &str) -> IResult<'_, &str> {
+    preceded(space0, expect("end of line", line_ending))(input)
+}
+
+fn rest_of_line(input: &str) -> IResult<'_, &str> {
+    map(not_line_ending, str::trim)(input)
+}
+
+fn bom(input: &str) -> IResult<'_, Option<char>> {
+    opt(char('\u{feff}'))(input)
 }
 
-fn description(input: &str) -> IResult<&str, Description> {
-    map(many0(comment), Description)(input)
+fn description(input: &str) -> IResult<'_, Description<'_>> {
+    map(many0(comment), Description::from)(input)
 }
 
-fn comment(input: &str) -> IResult<&str, &str> {
+/// A comment line, keeping the indentation following the `# ` for lists and code samples.
+fn comment(input: &str) -> IResult<'_, &str> {
     map(
         tuple((
             opt(indent),
             char('#'),
-            map(take_until("\n"), str::trim),
+            opt(char(' ')),
+            map(not_line_ending, str::trim_end),
             eol,
         )),
-        |(_, _, s, _eol)| s,
+        |(_, _, _, s, _eol)| s,
     )(input)
 }
 
-fn version(input: &str) -> IResult<&str, Version> {
-    map(
-        tuple((
-            tuple((tag("version"), eol)),
-            tuple((
-                indent,
-                tag("major"),
-                char(' '),
-                map_res(take_while(|c: char| c.is_ascii_digit()), FromStr::from_str),
-                eol,
-            )),
+fn version(input: &str) -> IResult<'_, Version<'_>> {
+    context(
+        "version",
+        map(
             tuple((
-                indent,
-                tag("minor"),
-                char(' '),
-                map_res(take_while(|c: char| c.is_ascii_digit()), FromStr::from_str),
-                eol,
+                description,
+                tuple((keyword("version"), eol)),
+                tuple((
+                    description,
+                    indent,
+                    keyword("major"),
+                    char(' '),
+                    number,
+                    eol,
+                )),
+                tuple((
+                    description,
+                    indent,
+                    keyword("minor"),
+                    char(' '),
+                    number,
+                    eol,
+                )),
             )),
-        )),
-        |((_version, _), (_, _major, _, major, _), (_, _minor, _, minor, _))| Version {
-            major,
-            minor,
-        },
+            |(
+                description,
+                (_version, _),
+                (major_description, _, _major, _, major, _),
+                (minor_description, _, _minor, _, minor, _),
+            )| Version {
+                major,
+                minor,
+                description,
+                major_description,
+                minor_description,
+            },
+        ),
     )(input)
 }
 
-fn domain(input: &str) -> IResult<&str, Domain> {
-    enum Item<'a> {
-        TypeDef(TypeDef<'a>),
-        Command(Command<'a>),
-        Event(Event<'a>),
-    }
+fn number(input: &str) -> IResult<'_, usize> {
+    expect(
+        "a number",
+        map_res(take_while(|c: char| c.is_ascii_digit()), FromStr::from_str),
+    )(input)
+}
 
-    map(
-        tuple((
-            description,
+fn name(input: &str) -> IResult<'_, &str> {
+    expect(
+        "a name",
+        verify(take_while(|c: char| !c.is_whitespace()), |s: &str| {
+            !s.is_empty()
+        }),
+    )(input)
+}
+
+fn domain(input: &str) -> IResult<'_, Domain<'_>> {
+    context(
+        "domain",
+        map(
             tuple((
-                optional("experimental"),
-                optional("deprecated"),
-                tag("domain"),
-                char(' '),
-                take_until("\n"),
-                eol,
+                domain_header,
+                many0(preceded(empty_lines, domain_item)),
+                position,
             )),
-            many0(depends_on),
-            many0(preceded(
-                empty_lines,
-                alt((
-                    map(type_def, Item::TypeDef),
-                    map(command, Item::Command),
-                    map(event, Item::Event),
+            |(mut domain, items, end)| {
+                for item in items {
+                    domain.push(item);
+                }
+                domain.span.end = end.len();
+                domain
+            },
+        ),
+    )(input)
+}
+
+fn domain_header(input: &str) -> IResult<'_, Domain<'_>> {
+    context(
+        "domain",
+        map(
+            tuple((
+                description,
+                tuple((
+                    position,
+                    modifiers(NODE_MODIFIERS, keyword("domain")),
+                    keyword("domain"),
+                    char(' '),
+                    rest_of_line,
+                    eol,
                 )),
+                many0(depends_on),
+                position,
             )),
-        )),
-        |(description, (experimental, deprecated, _domain, _, name, _eol), dependencies, items)| {
-            let (types, commands, events) = items.into_iter().fold(
-                (vec![], vec![], vec![]),
-                |(mut types, mut commands, mut events), item| {
-                    match item {
-                        Item::TypeDef(ty) => types.push(ty),
-                        Item::Command(cmd) => commands.push(cmd),
-                        Item::Event(evt) => events.push(evt),
-                    }
-
-                    (types, commands, events)
-                },
-            );
-
-            Domain {
+            |(description, (start, modifiers, _domain, _, name, _eol), dependencies, end)| Domain {
                 description,
-                experimental,
-                deprecated,
-                name,
+                experimental: modifiers.experimental,
+                deprecated: modifiers.deprecated,
+                annotations: modifiers.annotations,
+                name: name.into(),
                 dependencies,
-                types,
-                commands,
-                events,
-            }
-        },
+                types: vec![],
+                commands: vec![],
+                events: vec![],
+                span: span(start, end),
+            },
+        ),
     )(input)
 }
 
-fn depends_on(input: &str) -> IResult<&str, &str> {
-    map(
-        tuple((
-            indent,
-            tag("depends on"),
-            char(' '),
-            take_while(|c: char| !c.is_whitespace()),
-            eol,
-        )),
-        |(_, _depends_on, _, name, _eol)| name,
-    )(input)
+/// An item defined in a domain.
+enum DomainItem<'a> {
+    TypeDef(TypeDef<'a>),
+    Command(Command<'a>),
+    Event(Event<'a>),
 }
 
-fn type_def(input: &str) -> IResult<&str, TypeDef> {
-    map(
-        tuple((
-            description,
+fn domain_item(input: &str) -> IResult<'_, DomainItem<'_>> {
+    domain_item_with(&params)(input)
+}
+
+/// Parse an item of a domain, with `params` parsing its lists of parameters or properties.
+fn domain_item_with<'a, 'p, P>(
+    params: &'p P,
+) -> impl Fn(&'a str) -> IResult<'a, DomainItem<'a>> + 'p
+where
+    P: Fn(&'a str) -> IResult<'a, Vec<Param<'a>>>,
+{
+    move |input: &'a str| {
+        alt((
+            map(type_def(params), DomainItem::TypeDef),
+            map(command(params), DomainItem::Command),
+            map(event(params), DomainItem::Event),
+        ))(input)
+    }
+}
+
+/// The parameters or properties following a `parameters`, `returns` or `properties` line.
+fn params(input: &str) -> IResult<'_, Vec<Param<'_>>> {
+    many1(param)(input)
+}
+
+impl<'a> Domain<'a> {
+    fn push(&mut self, item: DomainItem<'a>) {
+        match item {
+            DomainItem::TypeDef(ty) => self.types.push(ty),
+            DomainItem::Command(cmd) => self.commands.push(cmd),
+            DomainItem::Event(evt) => self.events.push(evt),
+        }
+    }
+}
+
+fn depends_on(input: &str) -> IResult<'_, Dependency<'_>> {
+    context(
+        "depends_on",
+        map(
             tuple((
-                indent,
-                optional("experimental"),
-                optional("deprecated"),
-                tag("type"),
-                char(' '),
-                take_while(|c: char| !c.is_whitespace()),
-                char(' '),
-                tag("extends"),
-                char(' '),
-                ty,
-                eol,
+                description,
+                tuple((
+                    indent,
+                    position,
+                    keyword("depends on"),
+                    char(' '),
+                    name,
+                    eol,
+                    position,
+                )),
             )),
-            opt(item),
-        )),
-        |(
-            description,
-            (_, experimental, deprecated, _type, _, id, _, _extends, _, extends, _),
-            item,
-        )| {
-            let ty = TypeDef {
+            |(description, (_, start, _depends_on, _, name, _eol, end))| Dependency {
                 description,
-                experimental,
-                deprecated,
-                id,
-                extends,
-                item,
-            };
+                name: name.into(),
+                span: span(start, end),
+            },
+        ),
+    )(input)
+}
 
-            trace!("{:?}", ty);
+fn type_def<'a, 'p, P>(params: &'p P) -> impl Fn(&'a str) -> IResult<'a, TypeDef<'a>> + 'p
+where
+    P: Fn(&'a str) -> IResult<'a, Vec<Param<'a>>>,
+{
+    move |input: &'a str| {
+        context(
+            "type_def",
+            map(
+                tuple((
+                    description,
+                    tuple((
+                        indent,
+                        position,
+                        modifiers(NODE_MODIFIERS, keyword("type")),
+                        keyword("type"),
+                        char(' '),
+                        name,
+                        char(' '),
+                        keyword("extends"),
+                        char(' '),
+                        ty,
+                        eol,
+                    )),
+                    opt(item(params)),
+                    position,
+                )),
+                |(
+                    description,
+                    (_, start, modifiers, _type, _, id, _, _extends, _, extends, _),
+                    item,
+                    end,
+                )| {
+                    let ty = TypeDef {
+                        description,
+                        experimental: modifiers.experimental,
+                        deprecated: modifiers.deprecated,
+                        annotations: modifiers.annotations,
+                        id: id.into(),
+                        extends,
+                        item,
+                        span: span(start, end),
+                    };
 
-            ty
-        },
-    )(input)
+                    trace!("{:?}", ty);
+
+                    ty
+                },
+            ),
+        )(input)
+    }
 }
 
-fn ty(input: &str) -> IResult<&str, Type> {
-    map(
-        tuple((
-            optional("array of"),
-            take_while(|c: char| !c.is_whitespace()),
-        )),
-        |(is_array, ty)| Type::new(ty, is_array),
-    )(input)
+fn ty(input: &str) -> IResult<'_, Type<'_>> {
+    map(pair(optional("array of"), name), |(is_array, ty)| {
+        Type::new(ty, is_array)
+    })(input)
 }
 
 impl Type<'_> {
-    fn new(ty: &str, is_array: bool) -> Type {
+    fn new(ty: &str, is_array: bool) -> Type<'_> {
         if is_array {
             Type::ArrayOf(Box::new(Type::new(ty, false)))
         } else {
@@ -222,209 +449,481 @@ impl Type<'_> {
                 "object" => Type::Object,
                 "any" => Type::Any,
                 "binary" => Type::Binary,
-                _ => Type::Ref(ty),
+                _ => Type::Ref(ty.into()),
             }
         }
     }
 }
 
-fn item(input: &str) -> IResult<&str, Item> {
-    alt((
-        map(
-            preceded(tuple((indent, tag("enum"), eol)), many1(variant)),
-            Item::Enum,
-        ),
-        map(
-            preceded(tuple((indent, tag("properties"), eol)), many1(param)),
-            Item::Properties,
-        ),
-    ))(input)
+impl<'a> Type<'a> {
+    /// The variants of an inline `enum` or `array of enum`.
+    fn variants_mut(&mut self) -> Option<&mut Vec<Variant<'a>>> {
+        match self {
+            Type::Enum(variants) => Some(variants),
+            Type::ArrayOf(ty) => match **ty {
+                Type::Enum(ref mut variants) => Some(variants),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
 }
 
-fn variant(input: &str) -> IResult<&str, Variant> {
-    map(
-        tuple((
-            description,
+fn item<'a, 'p, P>(params: &'p P) -> impl Fn(&'a str) -> IResult<'a, Item<'a>> + 'p
+where
+    P: Fn(&'a str) -> IResult<'a, Vec<Param<'a>>>,
+{
+    move |input: &'a str| {
+        context(
+            "item",
+            alt((
+                map(
+                    preceded(tuple((indent, keyword("enum"), eol)), many1(variant)),
+                    Item::Enum,
+                ),
+                map(
+                    preceded(tuple((indent, keyword("properties"), eol)), params),
+                    Item::Properties,
+                ),
+            )),
+        )(input)
+    }
+}
+
+fn variant(input: &str) -> IResult<'_, Variant<'_>> {
+    context(
+        "variant",
+        map(
             tuple((
-                indent,
-                verify(take_while(|c: char| !c.is_whitespace()), |s: &str| {
-                    !s.is_empty() && s != "returns"
-                }),
-                eol,
+                description,
+                tuple((
+                    indent,
+                    position,
+                    expect(
+                        "an enum variant",
+                        verify(take_while(|c: char| !c.is_whitespace()), |s: &str| {
+                            !s.is_empty() && s != "returns"
+                        }),
+                    ),
+                    eol,
+                    position,
+                )),
             )),
-        )),
-        |(description, (_, name, _))| {
-            let variant = Variant { description, name };
+            |(description, (_, start, name, _, end))| {
+                let variant = Variant {
+                    description,
+                    name: name.into(),
+                    span: span(start, end),
+                };
 
-            trace!("{:?}", variant);
+                trace!("{:?}", variant);
 
-            variant
-        },
+                variant
+            },
+        ),
     )(input)
 }
 
-fn param(input: &str) -> IResult<&str, Param> {
-    let (input, mut param) = map(
-        tuple((
-            description,
+fn param(input: &str) -> IResult<'_, Param<'_>> {
+    context("param", |input| {
+        let (input, mut param) = map(
             tuple((
-                indent,
-                optional("experimental"),
-                optional("deprecated"),
-                optional("optional"),
-                ty,
-                char(' '),
-                verify(take_while(|c: char| !c.is_whitespace()), |s: &str| {
-                    !s.is_empty()
-                }),
-                eol,
-            )),
-        )),
-        |(description, (_, experimental, deprecated, optional, ty, _, name, _))| {
-            let param = Param {
-                experimental,
-                deprecated,
-                optional,
-                ty,
                 description,
-                name,
-            };
+                tuple((
+                    indent,
+                    position,
+                    modifiers(PARAM_MODIFIERS, tuple((ty, param_name, eol))),
+                    ty,
+                    param_name,
+                    eol,
+                )),
+            )),
+            |(description, (_, start, modifiers, ty, name, _))| {
+                let param = Param {
+                    experimental: modifiers.experimental,
+                    deprecated: modifiers.deprecated,
+                    optional: modifiers.optional,
+                    annotations: modifiers.annotations,
+                    ty,
+                    description,
+                    name: name.into(),
+                    span: span(start, start),
+                };
 
-            trace!("{:?}", param);
+                trace!("{:?}", param);
 
-            param
-        },
-    )(input)?;
+                param
+            },
+        )(input)?;
 
-    if let Type::Enum(ref mut variants) = param.ty {
-        let (input, mut vars) = many1(variant)(input)?;
+        let input = if let Some(variants) = param.ty.variants_mut() {
+            let (input, mut vars) = many1(variant)(input)?;
 
-        trace!("{:?}", vars);
+            trace!("{:?}", vars);
 
-        variants.append(&mut vars);
+            variants.append(&mut vars);
+
+            input
+        } else {
+            input
+        };
+
+        param.span.end = input.len();
 
         Ok((input, param))
-    } else {
-        Ok((input, param))
+    })(input)
+}
+
+/// The line introducing the return values of a command.
+fn returns(input: &str) -> IResult<'_, &str> {
+    recognize(tuple((empty_lines, indent, keyword("returns"), eol)))(input)
+}
+
+/// Whether the grammar goes on at `input` after a list of parameters or properties,
+/// with the return values of a command or with another item of the domain.
+fn after_params(input: &str) -> bool {
+    returns(input).is_ok() || domain_item(input).is_ok()
+}
+
+/// The name following the type of a parameter.
+fn param_name(input: &str) -> IResult<'_, &str> {
+    expect("a name", preceded(char(' '), name))(input)
+}
+
+fn command<'a, 'p, P>(params: &'p P) -> impl Fn(&'a str) -> IResult<'a, Command<'a>> + 'p
+where
+    P: Fn(&'a str) -> IResult<'a, Vec<Param<'a>>>,
+{
+    move |input: &'a str| {
+        context(
+            "command",
+            map(
+                tuple((
+                    description,
+                    tuple((
+                        indent,
+                        position,
+                        modifiers(NODE_MODIFIERS, keyword("command")),
+                        keyword("command"),
+                        char(' '),
+                        rest_of_line,
+                        eol,
+                    )),
+                    opt(redirect),
+                    opt(preceded(
+                        tuple((indent, keyword("parameters"), eol)),
+                        params,
+                    )),
+                    opt(preceded(returns, params)),
+                    position,
+                )),
+                |(
+                    description,
+                    (_, start, modifiers, _, _, name, _),
+                    redirect,
+                    parameters,
+                    returns,
+                    end,
+                )| {
+                    let command = Command {
+                        description,
+                        experimental: modifiers.experimental,
+                        deprecated: modifiers.deprecated,
+                        annotations: modifiers.annotations,
+                        name: name.into(),
+                        redirect,
+                        parameters: parameters.unwrap_or_default(),
+                        returns: returns.unwrap_or_default(),
+                        span: span(start, end),
+                    };
+
+                    trace!("{:?}", command);
+
+                    command
+                },
+            ),
+        )(input)
     }
 }
 
-fn command(input: &str) -> IResult<&str, Command> {
-    map(
-        tuple((
-            description,
-            tuple((
-                indent,
-                optional("experimental"),
-                optional("deprecated"),
-                tag("command"),
-                char(' '),
-                take_until("\n"),
-                eol,
-            )),
-            opt(redirect),
-            opt(preceded(
-                tuple((indent, tag("parameters"), eol)),
-                many1(param),
-            )),
-            empty_lines,
-            opt(preceded(tuple((indent, tag("returns"), eol)), many1(param))),
-        )),
-        |(
-            description,
-            (_, experimental, deprecated, _, _, name, _),
-            redirect,
-            parameters,
-            _,
-            returns,
-        )| {
-            let command = Command {
-                description,
-                experimental,
-                deprecated,
-                name,
-                redirect,
-                parameters: parameters.unwrap_or_default(),
-                returns: returns.unwrap_or_default(),
-            };
-
-            trace!("{:?}", command);
-
-            command
-        },
-    )(input)
+fn event<'a, 'p, P>(params: &'p P) -> impl Fn(&'a str) -> IResult<'a, Event<'a>> + 'p
+where
+    P: Fn(&'a str) -> IResult<'a, Vec<Param<'a>>>,
+{
+    move |input: &'a str| {
+        context(
+            "event",
+            map(
+                tuple((
+                    description,
+                    tuple((
+                        indent,
+                        position,
+                        modifiers(NODE_MODIFIERS, keyword("event")),
+                        keyword("event"),
+                        char(' '),
+                        rest_of_line,
+                        eol,
+                    )),
+                    opt(preceded(
+                        tuple((indent, keyword("parameters"), eol)),
+                        params,
+                    )),
+                    position,
+                )),
+                |(description, (_, start, modifiers, _, _, name, _), parameters, end)| {
+                    let event = Event {
+                        description,
+                        experimental: modifiers.experimental,
+                        deprecated: modifiers.deprecated,
+                        annotations: modifiers.annotations,
+                        name: name.into(),
+                        parameters: parameters.unwrap_or_default(),
+                        span: span(start, end),
+                    };
+
+                    trace!("{:?}", event);
+
+                    event
+                },
+            ),
+        )(input)
+    }
 }
 
-fn event(input: &str) -> IResult<&str, Event> {
-    map(
-        tuple((
-            description,
+fn redirect(input: &str) -> IResult<'_, Redirect<'_>> {
+    context(
+        "redirect",
+        map(
             tuple((
-                indent,
-                optional("experimental"),
-                optional("deprecated"),
-                tag("event"),
-                char(' '),
-                take_until("\n"),
-                eol,
-            )),
-            opt(preceded(
-                tuple((indent, tag("parameters"), eol)),
-                many1(param),
-            )),
-        )),
-        |(description, (_, experimental, deprecated, _, _, name, _), parameters)| {
-            let event = Event {
                 description,
-                experimental,
-                deprecated,
-                name,
-                parameters: parameters.unwrap_or_default(),
-            };
+                tuple((
+                    indent,
+                    position,
+                    keyword("redirect"),
+                    char(' '),
+                    name,
+                    eol,
+                    position,
+                )),
+            )),
+            |(description, (_, start, _redirect, _, to, _, end))| {
+                let redirect = Redirect {
+                    description,
+                    to: to.into(),
+                    span: span(start, end),
+                };
 
-            trace!("{:?}", event);
+                trace!("{:?}", redirect);
 
-            event
-        },
+                redirect
+            },
+        ),
     )(input)
 }
 
-fn redirect(input: &str) -> IResult<&str, Redirect> {
-    map(
-        tuple((
-            description,
-            tuple((
-                indent,
-                tag("redirect"),
-                char(' '),
-                take_while(|c: char| !c.is_whitespace()),
-                eol,
-            )),
-        )),
-        |(description, (_, _redirect, _, to, _))| {
-            let redirect = Redirect { description, to };
+/// Record where a node starts or ends, without consuming any input.
+fn position(input: &str) -> IResult<'_, &str> {
+    Ok((input, input))
+}
 
-            trace!("{:?}", redirect);
+/// The span between two positions, counted from the end of the input until it is located.
+fn span(start: &str, end: &str) -> Span {
+    Span {
+        start: start.len(),
+        end: end.len(),
+        ..Span::default()
+    }
+}
 
-            redirect
-        },
-    )(input)
+const NODE_MODIFIERS: &[&str] = &["experimental", "deprecated"];
+const PARAM_MODIFIERS: &[&str] = &["experimental", "deprecated", "optional"];
+
+/// The modifiers of a node; the `known` ones set a flag, the others are kept as annotations.
+#[derive(Default)]
+struct Modifiers<'a> {
+    experimental: bool,
+    deprecated: bool,
+    optional: bool,
+    annotations: Vec<Cow<'a, str>>,
+}
+
+impl<'a> Modifiers<'a> {
+    /// Add a modifier, returning `false` if it was already given.
+    fn insert(&mut self, known: &[&str], modifier: &'a str) -> bool {
+        let flag = match modifier {
+            "experimental" if known.contains(&modifier) => &mut self.experimental,
+            "deprecated" if known.contains(&modifier) => &mut self.deprecated,
+            "optional" if known.contains(&modifier) => &mut self.optional,
+            _ if self
+                .annotations
+                .iter()
+                .any(|annotation| annotation == modifier) =>
+            {
+                return false;
+            }
+            _ => {
+                self.annotations.push(modifier.into());
+                return true;
+            }
+        };
+
+        !std::mem::replace(flag, true)
+    }
+}
+
+/// The words which can't be modifiers, since they start or make up the rest of a line.
+const RESERVED: &[&str] = &[
+    "version",
+    "major",
+    "minor",
+    "include",
+    "domain",
+    "depends",
+    "on",
+    "type",
+    "extends",
+    "enum",
+    "properties",
+    "command",
+    "event",
+    "redirect",
+    "parameters",
+    "returns",
+    "array",
+    "of",
+    "integer",
+    "number",
+    "boolean",
+    "string",
+    "object",
+    "any",
+    "binary",
+];
+
+/// Whether a word can be a modifier, i.e. a lowercase word which isn't reserved.
+fn is_modifier(word: &str) -> bool {
+    word.starts_with(|c: char| c.is_ascii_lowercase())
+        && word
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+        && !RESERVED.contains(&word)
+}
+
+/// Parse the words before `next` on a line as modifiers, in any order.
+///
+/// `next` is only peeked, and if it doesn't match after any number of words,
+/// its error at the start of the modifiers is returned.
+fn modifiers<'a, O, F>(
+    known: &'static [&'static str],
+    next: F,
+) -> impl Fn(&'a str) -> IResult<'a, Modifiers<'a>>
+where
+    F: Fn(&'a str) -> IResult<'a, O>,
+{
+    move |input: &'a str| {
+        let err = match next(input) {
+            Ok(_) => return Ok((input, Modifiers::default())),
+            Err(err) => err,
+        };
+        let mut modifiers = Modifiers::default();
+        let mut duplicate = None;
+        let mut rest = input;
+
+        loop {
+            let (remaining, (word, _)) =
+                pair(verify(name, is_modifier), char(' '))(rest).map_err(|_| err.clone())?;
+
+            if !modifiers.insert(known, word) && duplicate.is_none() {
+                duplicate = Some(Error::duplicate(rest, word));
+            }
+
+            rest = remaining;
+
+            if next(rest).is_ok() {
+                return match duplicate {
+                    Some(err) => Err(nom::Err::Failure(err)),
+                    None => Ok((rest, modifiers)),
+                };
+            }
+        }
+    }
 }
 
-fn optional<T, I, E>(name: T) -> impl Fn(I) -> IResult<I, bool, E>
-where
-    T: InputLength + InputIter + Clone,
-    I: Slice<RangeFrom<usize>> + InputTake + InputTakeAtPosition + InputIter + Compare<T> + Clone,
-    <I as InputIter>::Item: AsChar,
-    <I as InputTakeAtPosition>::Item: AsChar + Clone,
-    E: ParseError<I>,
-{
-    map(opt(pair(tag(name), char(' '))), |v| v.is_some())
-}
+fn optional<'a>(name: &'static str) -> impl Fn(&'a str) -> IResult<'a, bool> {
+    map(opt(pair(tag(name), char(' '))), |v| v.is_some())
+}
+
+fn keyword<'a>(token: &'static str) -> impl Fn(&'a str) -> IResult<'a, &'a str> {
+    move |input: &'a str| {
+        tag(token)(input)
+            .map_err(|err| err.map(|_: Error| Error::expected(input, Expected::Token(token))))
+    }
+}
+
+/// Describe what `parser` expects when it fails without consuming any input.
+fn expect<'a, O, F>(what: &'static str, parser: F) -> impl Fn(&'a str) -> IResult<'a, O>
+where
+    F: Fn(&'a str) -> IResult<'a, O>,
+{
+    move |input: &'a str| {
+        parser(input).map_err(|err| {
+            err.map(|mut err| {
+                if err.input.len() == input.len() {
+                    err.expected = vec![Expected::Description(what)];
+                }
+                err
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub(super) const SNIPPET: &str = r#"# Copyright 2017 The Chromium Authors. All rights reserved.
+
+version
+  major 1
+  minor 3
+
+# This domain is deprecated - use Runtime or Log instead.
+deprecated domain Console
+  depends on Runtime
+
+  # Console message.
+  type ConsoleMessage extends object
+    properties
+      # Message source.
+      enum source
+        xml
+        javascript
+      # Message text.
+      string text
+
+  # Does nothing.
+  command clearMessages
+
+  # Returns the DER-encoded certificate.
+  experimental command getCertificate
+    parameters
+      # Origin to get certificate for.
+      string origin
+    returns
+      array of string tableNames
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+  # Hides any highlight.
+  command hideHighlight
+    # Use 'Overlay.hideHighlight' instead
+    redirect Overlay
+
+  # Issued when new console message is added.
+  event messageAdded
+    parameters
+      # Console message that has been added.
+      ConsoleMessage message
+"#;
 
     #[test]
     fn parse_protocol() {
@@ -472,48 +971,57 @@ experimental domain Accessibility
                         "found in the LICENSE file."
                     ]
                     .into(),
-                    version: Version { major: 1, minor: 3 },
+                    version: Version::new(1, 3),
                     domains: vec![Domain {
                         description: Default::default(),
                         experimental: true,
                         deprecated: false,
-                        name: "Accessibility",
-                        dependencies: vec!["DOM"],
+                        annotations: vec![],
+                        name: "Accessibility".into(),
+                        dependencies: vec!["DOM".into()],
                         types: vec![
                             TypeDef {
                                 description: "Unique accessibility node identifier.".into(),
                                 experimental: false,
                                 deprecated: false,
-                                id: "AXNodeId",
+                                annotations: vec![],
+                                id: "AXNodeId".into(),
                                 extends: Type::String,
                                 item: None,
+                                span: Default::default(),
                             },
                             TypeDef {
                                 description: "Enum of possible property types.".into(),
                                 experimental: false,
                                 deprecated: false,
-                                id: "AXValueType",
+                                annotations: vec![],
+                                id: "AXValueType".into(),
                                 extends: Type::String,
                                 item: Some(Item::Enum(vec![
                                     Variant {
                                         description: Default::default(),
-                                        name: "boolean"
+                                        name: "boolean".into(),
+                                        span: Default::default(),
                                     },
                                     Variant {
                                         description: Default::default(),
-                                        name: "tristate"
+                                        name: "tristate".into(),
+                                        span: Default::default(),
                                     },
                                     Variant {
                                         description: Default::default(),
-                                        name: "booleanOrUndefined"
+                                        name: "booleanOrUndefined".into(),
+                                        span: Default::default(),
                                     }
-                                ]))
+                                ])),
+                                span: Default::default(),
                             },
                             TypeDef {
                                 description: "A single source for a computed AX property.".into(),
                                 experimental: false,
                                 deprecated: false,
-                                id: "AXValueSource",
+                                annotations: vec![],
+                                id: "AXValueSource".into(),
                                 extends: Type::Object,
                                 item: Some(Item::Properties(vec![
                                     Param {
@@ -521,16 +1029,20 @@ experimental domain Accessibility
                                         experimental: false,
                                         deprecated: false,
                                         optional: false,
-                                        ty: Type::Ref("AXValueSourceType"),
-                                        name: "type"
+                                        annotations: vec![],
+                                        ty: Type::Ref("AXValueSourceType".into()),
+                                        name: "type".into(),
+                                        span: Default::default(),
                                     },
                                     Param {
                                         description: "The value of this property source.".into(),
                                         experimental: false,
                                         deprecated: false,
                                         optional: true,
-                                        ty: Type::Ref("AXValue"),
-                                        name: "value"
+                                        annotations: vec![],
+                                        ty: Type::Ref("AXValue".into()),
+                                        name: "value".into(),
+                                        span: Default::default(),
                                     },
                                     Param {
                                         description: "The name of the relevant attribute, if any."
@@ -538,14 +1050,18 @@ experimental domain Accessibility
                                         experimental: false,
                                         deprecated: false,
                                         optional: true,
+                                        annotations: vec![],
                                         ty: Type::String,
-                                        name: "attribute"
+                                        name: "attribute".into(),
+                                        span: Default::default(),
                                     }
-                                ]))
+                                ])),
+                                span: Default::default(),
                             }
                         ],
                         commands: vec![],
-                        events: vec![]
+                        events: vec![],
+                        span: Default::default(),
                     }],
                 }
             )
@@ -573,7 +1089,7 @@ experimental domain Accessibility
 "#
             )
             .unwrap(),
-            ("", Version { major: 1, minor: 3 })
+            ("", Version::new(1, 3))
         )
     }
 
@@ -587,11 +1103,13 @@ experimental domain Accessibility
                     description: Default::default(),
                     experimental: true,
                     deprecated: false,
-                    name: "Accessibility",
+                    annotations: vec![],
+                    name: "Accessibility".into(),
                     dependencies: vec![],
                     types: vec![],
                     commands: vec![],
                     events: vec![],
+                    span: Default::default(),
                 }
             )
         );
@@ -599,13 +1117,16 @@ experimental domain Accessibility
 
     #[test]
     fn parse_depends_on() {
-        assert_eq!(depends_on("  depends on DOM\n").unwrap(), ("", "DOM"));
+        assert_eq!(
+            depends_on("  depends on DOM\n").unwrap(),
+            ("", "DOM".into())
+        );
     }
 
     #[test]
     fn parse_type_def() {
         assert_eq!(
-            type_def(
+            type_def(&params)(
                 r#"  type AXProperty extends object
     properties
       # The name of this property.
@@ -621,7 +1142,8 @@ experimental domain Accessibility
                     description: Default::default(),
                     experimental: false,
                     deprecated: false,
-                    id: "AXProperty",
+                    annotations: vec![],
+                    id: "AXProperty".into(),
                     extends: Type::Object,
                     item: Some(Item::Properties(vec![
                         Param {
@@ -629,18 +1151,23 @@ experimental domain Accessibility
                             experimental: false,
                             deprecated: false,
                             optional: false,
-                            ty: Type::Ref("AXPropertyName"),
-                            name: "name"
+                            annotations: vec![],
+                            ty: Type::Ref("AXPropertyName".into()),
+                            name: "name".into(),
+                            span: Default::default(),
                         },
                         Param {
                             description: "The value of this property.".into(),
                             experimental: false,
                             deprecated: false,
                             optional: false,
-                            ty: Type::Ref("AXValue"),
-                            name: "value"
+                            annotations: vec![],
+                            ty: Type::Ref("AXValue".into()),
+                            name: "value".into(),
+                            span: Default::default(),
                         }
-                    ]))
+                    ])),
+                    span: Default::default(),
                 }
             )
         )
@@ -649,7 +1176,7 @@ experimental domain Accessibility
     #[test]
     fn parse_enum() {
         assert_eq!(
-            type_def(
+            type_def(&params)(
                 r#"  # Enum of possible property sources.
   type AXValueSourceType extends string
     enum
@@ -668,40 +1195,48 @@ experimental domain Accessibility
                     description: "Enum of possible property sources.".into(),
                     experimental: false,
                     deprecated: false,
-                    id: "AXValueSourceType",
+                    annotations: vec![],
+                    id: "AXValueSourceType".into(),
                     extends: Type::String,
                     item: Some(Item::Enum(vec![
                         Variant {
                             description: Default::default(),
-                            name: "attribute"
+                            name: "attribute".into(),
+                            span: Default::default(),
                         },
                         Variant {
                             description: Default::default(),
-                            name: "implicit"
+                            name: "implicit".into(),
+                            span: Default::default(),
                         },
                         Variant {
                             description: Default::default(),
-                            name: "style"
+                            name: "style".into(),
+                            span: Default::default(),
                         },
                         Variant {
                             description: Default::default(),
-                            name: "contents"
+                            name: "contents".into(),
+                            span: Default::default(),
                         },
                         Variant {
                             description: Default::default(),
-                            name: "placeholder"
+                            name: "placeholder".into(),
+                            span: Default::default(),
                         },
                         Variant {
                             description: Default::default(),
-                            name: "relatedElement"
+                            name: "relatedElement".into(),
+                            span: Default::default(),
                         }
-                    ]))
+                    ])),
+                    span: Default::default(),
                 }
             )
         );
 
         assert_eq!(
-            type_def(
+            type_def(&params)(
                 r#"  # Pseudo element type.
   type PseudoType extends string
     enum
@@ -717,22 +1252,27 @@ experimental domain Accessibility
                     description: "Pseudo element type.".into(),
                     experimental: false,
                     deprecated: false,
-                    id: "PseudoType",
+                    annotations: vec![],
+                    id: "PseudoType".into(),
                     extends: Type::String,
                     item: Some(Item::Enum(vec![
                         Variant {
                             description: Default::default(),
-                            name: "first-line"
+                            name: "first-line".into(),
+                            span: Default::default(),
                         },
                         Variant {
                             description: Default::default(),
-                            name: "first-letter"
+                            name: "first-letter".into(),
+                            span: Default::default(),
                         },
                         Variant {
                             description: Default::default(),
-                            name: "before"
+                            name: "before".into(),
+                            span: Default::default(),
                         }
-                    ]))
+                    ])),
+                    span: Default::default(),
                 }
             )
         );
@@ -741,7 +1281,7 @@ experimental domain Accessibility
     #[test]
     fn parse_params() {
         assert_eq!(
-            item(
+            item(&params)(
                 r#"    properties
       # The type of this value.
       AXValueType type
@@ -765,36 +1305,44 @@ experimental domain Accessibility
                         experimental: false,
                         deprecated: false,
                         optional: false,
-                        ty: Type::Ref("AXValueType"),
-                        name: "type"
+                        annotations: vec![],
+                        ty: Type::Ref("AXValueType".into()),
+                        name: "type".into(),
+                        span: Default::default(),
                     },
                     Param {
                         description: "The computed value of this property.".into(),
                         experimental: false,
                         deprecated: false,
                         optional: true,
+                        annotations: vec![],
                         ty: Type::Any,
-                        name: "value"
+                        name: "value".into(),
+                        span: Default::default(),
                     },
                     Param {
                         description: "One or more related nodes, if applicable.".into(),
                         experimental: false,
                         deprecated: false,
                         optional: true,
-                        ty: Type::ArrayOf(Box::new(Type::Ref("AXRelatedNode"))),
-                        name: "relatedNodes"
+                        annotations: vec![],
+                        ty: Type::ArrayOf(Box::new(Type::Ref("AXRelatedNode".into()))),
+                        name: "relatedNodes".into(),
+                        span: Default::default(),
                     },
                     Param {
                         description: "Animation type of `Animation`.".into(),
                         experimental: false,
                         deprecated: false,
                         optional: false,
+                        annotations: vec![],
                         ty: Type::Enum(vec![
                             Variant::new("CSSTransition"),
                             Variant::new("CSSAnimation"),
                             Variant::new("WebAnimation"),
                         ]),
-                        name: "type"
+                        name: "type".into(),
+                        span: Default::default(),
                     }
                 ])
             )
@@ -804,7 +1352,7 @@ experimental domain Accessibility
     #[test]
     fn parse_command() {
         assert_eq!(
-            command(
+            command(&params)(
                 r#"  # Returns the DER-encoded certificate.
   experimental command getCertificate
     parameters
@@ -821,30 +1369,36 @@ experimental domain Accessibility
                     description: "Returns the DER-encoded certificate.".into(),
                     experimental: true,
                     deprecated: false,
-                    name: "getCertificate",
+                    annotations: vec![],
+                    name: "getCertificate".into(),
                     redirect: None,
                     parameters: vec![Param {
                         description: "Origin to get certificate for.".into(),
                         experimental: false,
                         deprecated: false,
                         optional: false,
+                        annotations: vec![],
                         ty: Type::String,
-                        name: "origin"
+                        name: "origin".into(),
+                        span: Default::default(),
                     }],
                     returns: vec![Param {
                         description: Default::default(),
                         experimental: false,
                         deprecated: false,
                         optional: false,
+                        annotations: vec![],
                         ty: Type::ArrayOf(Box::new(Type::String)),
-                        name: "tableNames"
+                        name: "tableNames".into(),
+                        span: Default::default(),
                     }],
+                    span: Default::default(),
                 }
             )
         );
 
         assert_eq!(
-            command(
+            command(&params)(
                 r#"  # Hides any highlight.
   command hideHighlight
     # Use 'Overlay.hideHighlight' instead
@@ -858,13 +1412,16 @@ experimental domain Accessibility
                     description: "Hides any highlight.".into(),
                     experimental: false,
                     deprecated: false,
-                    name: "hideHighlight",
+                    annotations: vec![],
+                    name: "hideHighlight".into(),
                     redirect: Some(Redirect {
                         description: "Use 'Overlay.hideHighlight' instead".into(),
-                        to: "Overlay"
+                        to: "Overlay".into(),
+                        span: Default::default(),
                     }),
                     parameters: vec![],
                     returns: vec![],
+                    span: Default::default(),
                 }
             )
         );
@@ -873,7 +1430,7 @@ experimental domain Accessibility
     #[test]
     fn parse_event() {
         assert_eq!(
-            event(r#"  # Notification sent after the virtual time has advanced.
+            event(&params)(r#"  # Notification sent after the virtual time has advanced.
   experimental event virtualTimeAdvanced
     parameters
       # The amount of virtual time that has elapsed in milliseconds since virtual time was first
@@ -886,7 +1443,8 @@ experimental domain Accessibility
                     description: "Notification sent after the virtual time has advanced.".into(),
                     experimental: true,
                     deprecated: false,
-                    name: "virtualTimeAdvanced",
+                    annotations: vec![],
+                    name: "virtualTimeAdvanced".into(),
                     parameters: vec![
                         Param {
                             description: vec![
@@ -896,10 +1454,13 @@ experimental domain Accessibility
                             experimental: false,
                             deprecated: false,
                             optional: false,
+                            annotations: vec![],
                             ty: Type::Number,
-                            name: "virtualTimeElapsed"
+                            name: "virtualTimeElapsed".into(),
+                            span: Default::default(),
                         },
                     ],
+                    span: Default::default(),
                 }
             )
         );
@@ -918,9 +1479,605 @@ experimental domain Accessibility
                 "",
                 Redirect {
                     description: "Use 'Emulation.clearGeolocationOverride' instead".into(),
-                    to: "Emulation"
+                    to: "Emulation".into(),
+                    span: Default::default(),
+                }
+            )
+        )
+    }
+
+    #[test]
+    fn parse_error() {
+        let err = parse(
+            r#"# Copyright 2017 The Chromium Authors. All rights reserved.
+
+version
+  major 1
+  mnor 3
+"#,
+        )
+        .unwrap_err();
+
+        assert_eq!(err.line(), 5);
+        assert_eq!(err.column(), 3);
+        assert_eq!(err.source_line(), "  mnor 3");
+        assert_eq!(err.rule(), Some("version"));
+        assert_eq!(err.expected(), ["`minor`"]);
+        assert_eq!(
+            err.to_string(),
+            r#"5:3: expected `minor` in version
+  |
+5 |   mnor 3
+  |   ^"#
+        );
+
+        let err = parse(
+            r#"version
+  major 1
+  minor 3
+
+experimental domain
+"#,
+        )
+        .unwrap_err();
+
+        assert_eq!((err.line(), err.column()), (5, 20));
+        assert_eq!(err.rule(), Some("domain"));
+        assert_eq!(err.expected(), ["' '"]);
+
+        let err = type_def(&params)(
+            "  type AXNodeId extends
+",
+        )
+        .unwrap_err();
+
+        match err {
+            nom::Err::Error(err) => {
+                assert_eq!(err.input, "\n");
+                assert_eq!(err.rule, Some("type_def"));
+                assert_eq!(err.expected, vec![Expected::Char(' ')]);
+            }
+            _ => panic!("unexpected error: {:?}", err),
+        }
+
+        let err = alt((
+            map(type_def(&params), |_| ()),
+            map(command(&params), |_| ()),
+        ))("  typo AXNodeId extends string\n")
+        .unwrap_err();
+
+        match err {
+            nom::Err::Error(err) => {
+                assert_eq!(err.input, "typo AXNodeId extends string\n");
+                assert_eq!(
+                    err.expected,
+                    vec![Expected::Token("type"), Expected::Token("command")]
+                );
+            }
+            _ => panic!("unexpected error: {:?}", err),
+        }
+    }
+
+    #[test]
+    fn parse_crlf() {
+        let (rest, protocol) = parse(SNIPPET).unwrap();
+
+        assert_eq!(rest, "");
+        assert_eq!(protocol.domains[0].commands.len(), 3);
+        assert_eq!(protocol.domains[0].events.len(), 1);
+
+        let crlf = SNIPPET.replace('\n', "\r\n");
+
+        assert_eq!(parse(&crlf).unwrap(), ("", protocol));
+    }
+
+    #[test]
+    fn parse_bom() {
+        let bom = format!("\u{feff}{}", SNIPPET);
+
+        assert_eq!(parse(&bom).unwrap(), parse(SNIPPET).unwrap());
+    }
+
+    #[test]
+    fn parse_trailing_whitespace() {
+        let trailing = SNIPPET.replace('\n', " \t\n");
+
+        assert_eq!(parse(&trailing).unwrap(), parse(SNIPPET).unwrap());
+
+        let trailing = SNIPPET.replace('\n', "  \r\n");
+
+        assert_eq!(parse(&trailing).unwrap(), parse(SNIPPET).unwrap());
+    }
+
+    #[test]
+    fn parse_tab_indent() {
+        let tabs = SNIPPET
+            .lines()
+            .map(|line| {
+                let trimmed = line.trim_start_matches(' ');
+
+                "\t".repeat((line.len() - trimmed.len()) / 2) + trimmed + "\n"
+            })
+            .collect::<String>();
+
+        assert_eq!(parse(&tabs).unwrap(), parse(SNIPPET).unwrap());
+    }
+
+    #[test]
+    fn parse_spans() {
+        let (_, protocol) = parse(SNIPPET).unwrap();
+        let domain = &protocol.domains[0];
+        let text = |node: &dyn Spanned| &SNIPPET[node.span().range()];
+
+        assert_eq!((domain.span.line, domain.span.column), (8, 1));
+        assert!(text(domain).starts_with("deprecated domain Console\n  depends on Runtime\n"));
+        assert!(text(domain).ends_with("      ConsoleMessage message\n"));
+
+        let ty = &domain.types[0];
+
+        assert_eq!((ty.span.line, ty.span.column), (12, 3));
+        assert_eq!(
+            text(ty),
+            r#"type ConsoleMessage extends object
+    properties
+      # Message source.
+      enum source
+        xml
+        javascript
+      # Message text.
+      string text
+"#
+        );
+
+        match ty.item {
+            Some(Item::Properties(ref props)) => {
+                assert_eq!((props[0].span.line, props[0].span.column), (15, 7));
+                assert_eq!(
+                    text(&props[0]),
+                    "enum source\n        xml\n        javascript\n"
+                );
+                assert_eq!(text(&props[1]), "string text\n");
+
+                match props[0].ty {
+                    Type::Enum(ref variants) => {
+                        assert_eq!((variants[1].span.line, variants[1].span.column), (17, 9));
+                        assert_eq!(text(&variants[1]), "javascript\n");
+                    }
+                    _ => panic!("unexpected type: {:?}", props[0].ty),
                 }
+            }
+            _ => panic!("unexpected item: {:?}", ty.item),
+        }
+
+        assert_eq!(text(&domain.commands[0]), "command clearMessages\n");
+
+        let redirect = domain.commands[2].redirect.as_ref().unwrap();
+
+        assert_eq!((redirect.span.line, redirect.span.column), (35, 5));
+        assert_eq!(text(redirect), "redirect Overlay\n");
+        assert_eq!(text(&domain.events[0]).lines().count(), 4);
+    }
+
+    #[test]
+    fn compare_spans() {
+        let (_, protocol) = parse(SNIPPET).unwrap();
+        let mut moved = protocol.clone();
+
+        moved.domains[0].span = Span::default();
+        moved.domains[0].types[0].span = Span::default();
+
+        assert_eq!(moved, protocol);
+        assert_ne!(moved.domains[0].span, protocol.domains[0].span);
+        assert_ne!(
+            Diagnostic::error("syntax", "oops", moved.domains[0].span),
+            Diagnostic::error("syntax", "oops", protocol.domains[0].span)
+        );
+    }
+
+    #[test]
+    fn parse_whole_input() {
+        assert_eq!(parse_str(SNIPPET).unwrap(), parse(SNIPPET).unwrap().1);
+        assert_eq!(
+            Protocol::try_from(format!("{}\n\n", SNIPPET).as_str()).unwrap(),
+            parse(SNIPPET).unwrap().1
+        );
+
+        let err = parse_str(&SNIPPET.replace("  command clearMessages", "  comand clearMessages"))
+            .unwrap_err();
+
+        assert_eq!((err.line(), err.column()), (22, 3));
+        assert_eq!(err.source_line(), "  comand clearMessages");
+        assert_eq!(err.message(), "expected `type`, `command` or `event`");
+
+        let err = parse_str(&format!("{}\nfoo bar\n", SNIPPET)).unwrap_err();
+
+        assert_eq!((err.line(), err.column()), (43, 1));
+        assert_eq!(err.source_line(), "foo bar");
+    }
+
+    #[test]
+    fn parse_array_of_enum() {
+        assert_eq!(
+            item(&params)(
+                r#"    properties
+      # The reasons why request was blocked.
+      optional array of enum blockedReasons
+        other
+        csp
+        mixed-content
+      # Request URL.
+      string url
+"#
+            )
+            .unwrap(),
+            (
+                "",
+                Item::Properties(vec![
+                    Param {
+                        description: "The reasons why request was blocked.".into(),
+                        experimental: false,
+                        deprecated: false,
+                        optional: true,
+                        annotations: vec![],
+                        ty: Type::ArrayOf(Box::new(Type::Enum(vec![
+                            Variant::new("other"),
+                            Variant::new("csp"),
+                            Variant::new("mixed-content"),
+                        ]))),
+                        name: "blockedReasons".into(),
+                        span: Default::default(),
+                    },
+                    Param {
+                        description: "Request URL.".into(),
+                        experimental: false,
+                        deprecated: false,
+                        optional: false,
+                        annotations: vec![],
+                        ty: Type::String,
+                        name: "url".into(),
+                        span: Default::default(),
+                    }
+                ])
             )
+        );
+    }
+
+    #[cfg(any(feature = "display", feature = "to_json"))]
+    const ARRAY_OF_ENUM: &str = r#"version
+  major 1
+  minor 3
+
+domain Emulation
+
+  # Emulates the given media type or media feature for CSS media queries.
+  command setEmulatedMedia
+    parameters
+      # Media type to emulate. Empty string disables the override.
+      optional string media
+      # Media features to emulate.
+      optional array of enum features
+        prefers-color-scheme
+        prefers-reduced-motion
+
+"#;
+
+    #[cfg(feature = "display")]
+    #[test]
+    fn display_array_of_enum() {
+        let protocol = parse_str(ARRAY_OF_ENUM).unwrap();
+
+        assert_eq!(protocol.to_string(), ARRAY_OF_ENUM);
+        assert_eq!(parse_str(&protocol.to_string()).unwrap(), protocol);
+    }
+
+    #[cfg(feature = "to_json")]
+    #[test]
+    fn serialize_array_of_enum() {
+        let protocol = parse_str(ARRAY_OF_ENUM).unwrap();
+        let param = &protocol.domains[0].commands[0].parameters[1];
+
+        assert_eq!(
+            serde_json::to_value(param).unwrap(),
+            serde_json::json!({
+                "name": "features",
+                "description": "Media features to emulate.",
+                "optional": true,
+                "type": "array",
+                "items": {
+                    "type": "string",
+                    "enum": ["prefers-color-scheme", "prefers-reduced-motion"]
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn parse_into_owned() {
+        let source = SNIPPET.to_owned();
+        let protocol = parse_str(&source).unwrap().into_owned();
+
+        drop(source);
+
+        let protocol = std::thread::spawn(move || protocol).join().unwrap();
+
+        assert_eq!(protocol, parse_str(SNIPPET).unwrap());
+        assert_eq!(protocol, SNIPPET.parse().unwrap());
+    }
+
+    #[test]
+    fn parse_paragraphs() {
+        let (_, command) = command(&params)(
+            r#"  # Set the behavior when downloading a file.
+  #
+  # Allowed values:
+  # - `deny`: downloads are denied.
+  # - `allow`: downloads are allowed,
+  #   and saved to `downloadPath`.
+  #
+  #
+  #     setDownloadBehavior({behavior: "deny"})
+  experimental command setDownloadBehavior
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            &command.description[..],
+            &[
+                "Set the behavior when downloading a file.",
+                "",
+                "Allowed values:",
+                "- `deny`: downloads are denied.",
+                "- `allow`: downloads are allowed,",
+                "  and saved to `downloadPath`.",
+                "",
+                "",
+                "    setDownloadBehavior({behavior: \"deny\"})",
+            ]
+        );
+        assert_eq!(command.description.paragraphs().count(), 3);
+        assert_eq!(
+            command.description.joined(),
+            "Set the behavior when downloading a file.\n\nAllowed values:\n- `deny`: downloads are denied.\n- `allow`: downloads are allowed,\n  and saved to `downloadPath`.\n\n\n    setDownloadBehavior({behavior: \"deny\"})"
+        );
+        assert_eq!(
+            command.description.to_markdown(),
+            "Set the behavior when downloading a file.\n\nAllowed values:\n- `deny`: downloads are denied.\n- `allow`: downloads are allowed,\n  and saved to `downloadPath`.\n\n    setDownloadBehavior({behavior: \"deny\"})"
+        );
+    }
+
+    #[cfg(feature = "to_json")]
+    #[test]
+    fn serialize_paragraphs() {
+        let (_, command) = command(&params)(
+            r#"  # Set the behavior when downloading a file.
+  #
+  # Allowed values.
+  command setDownloadBehavior
+"#,
         )
+        .unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&command).unwrap(),
+            serde_json::json!({
+                "name": "setDownloadBehavior",
+                "description": "Set the behavior when downloading a file.  Allowed values."
+            })
+        );
+    }
+
+    #[cfg(feature = "display")]
+    #[test]
+    fn display_paragraphs() {
+        let description = Description::from(vec!["Allowed values:", "", "- `deny`", "  continued"]);
+
+        assert_eq!(
+            description.to_string(),
+            "# Allowed values:\n#\n# - `deny`\n#   continued\n"
+        );
+        assert_eq!(
+            self::description(&description.to_string()).unwrap(),
+            ("", description)
+        );
+    }
+
+    pub(super) const MODIFIERS: &str = r#"version
+  major 1
+  minor 3
+
+internal domain Storage
+
+  deprecated experimental internal command clearDataForOrigin
+    parameters
+      # Security origin.
+      string origin
+      internal optional boolean force
+
+"#;
+
+    #[test]
+    fn parse_modifiers() {
+        let protocol = parse_str(MODIFIERS).unwrap();
+        let domain = &protocol.domains[0];
+        let command = &domain.commands[0];
+
+        assert_eq!(domain.annotations, vec!["internal"]);
+        assert!(command.experimental && command.deprecated);
+        assert_eq!(command.annotations, vec!["internal"]);
+        assert!(command.parameters[1].optional);
+        assert_eq!(command.parameters[1].annotations, vec!["internal"]);
+
+        let err = parse_str(&MODIFIERS.replace("internal optional", "optional internal optional"))
+            .unwrap_err();
+
+        assert_eq!(err.duplicate(), Some("optional"));
+        assert_eq!(
+            err.to_string(),
+            r#"11:25: duplicate modifier `optional` in param
+   |
+11 |       optional internal optional boolean force
+   |                         ^"#
+        );
+    }
+
+    #[cfg(feature = "display")]
+    #[test]
+    fn display_modifiers() {
+        let protocol = parse_str(MODIFIERS).unwrap();
+        let text = protocol.to_string();
+
+        // known modifiers come first, in their canonical order
+        assert!(text.contains("\n  experimental deprecated internal command clearDataForOrigin\n"));
+        assert!(text.contains("\n      optional internal boolean force\n"));
+        assert_eq!(parse_str(&text).unwrap(), protocol);
+    }
+
+    #[cfg(feature = "to_json")]
+    #[test]
+    fn serialize_modifiers() {
+        let protocol = parse_str(MODIFIERS).unwrap();
+        let command = &protocol.domains[0].commands[0];
+
+        assert_eq!(
+            serde_json::to_value(command).unwrap(),
+            serde_json::json!({
+                "experimental": true,
+                "deprecated": true,
+                "annotations": ["internal"],
+                "name": "clearDataForOrigin",
+                "parameters": [
+                    {
+                        "name": "origin",
+                        "description": "Security origin.",
+                        "type": "string"
+                    },
+                    {
+                        "name": "force",
+                        "optional": true,
+                        "annotations": ["internal"],
+                        "type": "boolean"
+                    }
+                ]
+            })
+        );
+    }
+
+    pub(super) const DESCRIPTIONS: &str = r#"# Copyright.
+
+# The version of the protocol.
+version
+  # Bumped on breaking changes.
+  major 1
+  # Bumped on additions.
+  minor 3
+
+domain Page
+  # For the frame tree.
+  depends on DOM
+  depends on Network
+
+  command setDeviceMetricsOverride
+    # Moved to the emulation domain.
+    redirect Emulation
+
+"#;
+
+    #[test]
+    fn parse_descriptions() {
+        let protocol = parse_str(DESCRIPTIONS).unwrap();
+        let version = &protocol.version;
+        let domain = &protocol.domains[0];
+
+        assert_eq!(&protocol.description[..], &["Copyright."]);
+        assert_eq!(&version.description[..], &["The version of the protocol."]);
+        assert_eq!(
+            &version.major_description[..],
+            &["Bumped on breaking changes."]
+        );
+        assert_eq!(&version.minor_description[..], &["Bumped on additions."]);
+        assert_eq!(
+            &domain.dependencies[0].description[..],
+            &["For the frame tree."]
+        );
+        assert!(domain.dependencies[1].description.is_empty());
+        assert_eq!(
+            (
+                domain.dependencies[1].span.line,
+                domain.dependencies[1].span.column
+            ),
+            (13, 3)
+        );
+        assert_eq!(
+            &domain.commands[0].redirect.as_ref().unwrap().description[..],
+            &["Moved to the emulation domain."]
+        );
+    }
+
+    #[cfg(feature = "display")]
+    #[test]
+    fn display_descriptions() {
+        let protocol = parse_str(DESCRIPTIONS).unwrap();
+        let text = protocol.to_string();
+
+        assert!(text.starts_with(
+            r#"# Copyright.
+
+# The version of the protocol.
+version
+  # Bumped on breaking changes.
+  major 1
+  # Bumped on additions.
+  minor 3
+"#
+        ));
+        assert!(text.contains("\n  # For the frame tree.\n  depends on DOM\n"));
+        assert!(text.contains("\n    # Moved to the emulation domain.\n    redirect Emulation\n"));
+        assert_eq!(parse_str(&text).unwrap(), protocol);
+    }
+
+    #[cfg(feature = "display")]
+    #[test]
+    fn display_version_description() {
+        let mut protocol = parse_str("version\n  major 1\n  minor 3\n\ndomain Page\n").unwrap();
+
+        protocol.version.description = Description::from("The version.");
+
+        let text = protocol.to_string();
+
+        assert!(text.starts_with("# The version.\nversion\n"));
+
+        // without a description of the protocol, nothing tells the comments of the version apart
+        let reparsed = parse_str(&text).unwrap();
+
+        assert_eq!(&reparsed.description[..], &["The version."]);
+        assert!(reparsed.version.description.is_empty());
+        assert_eq!(reparsed.to_string(), text);
+    }
+
+    #[cfg(feature = "to_json")]
+    #[test]
+    fn serialize_descriptions() {
+        let protocol = parse_str(DESCRIPTIONS).unwrap();
+        let domain = &protocol.domains[0];
+
+        assert_eq!(
+            serde_json::to_value(&protocol.version).unwrap(),
+            serde_json::json!({
+                "major": "1",
+                "minor": "3"
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(&domain.dependencies).unwrap(),
+            serde_json::json!(["DOM", "Network"])
+        );
+        assert_eq!(
+            serde_json::to_value(&domain.commands[0]).unwrap(),
+            serde_json::json!({
+                "name": "setDeviceMetricsOverride",
+                "redirect": "Emulation"
+            })
+        );
     }
 }
@@ -0,0 +1,1066 @@
+//! A lossless concrete syntax tree of PDL sources.
+//!
+//! Unlike the `Protocol` AST, the syntax tree keeps every byte of the source,
+//! including blank lines, comments, indentation and line endings, so a file can
+//! be edited in place and written back with all untouched text byte-identical.
+//!
+//! The tree follows the line structure of PDL: each node starts with its
+//! description comments and its header line, followed by the more indented
+//! lines nested in it. Domains, items, parameters and variants are only made
+//! nodes of where `parse_recovering` finds them, so the tree and the grammar
+//! agree even on malformed or loosely indented sources. Building a tree never
+//! fails; lines that the grammar rejects become `NodeKind::Unknown` nodes.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+
+use crate::{Item, Param, Type, Variant};
+
+/// The kind of a `Token`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    /// The UTF-8 byte order mark at the start of the source.
+    Bom,
+    /// Indentation, spaces between words or trailing whitespace.
+    Whitespace,
+    /// A line ending, either `\n` or `\r\n`.
+    Newline,
+    /// A comment, from `#` to the end of the line.
+    Comment,
+    /// A keyword or modifier, e.g. `domain` or `optional`.
+    Keyword,
+    /// A name, type reference, path or enum variant.
+    Name,
+    /// The number of a `major` or `minor` version.
+    Number,
+}
+
+/// A leaf of the syntax tree, covering a slice of the source.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    pub text: &'a str,
+    /// The byte offset of the token in the source.
+    pub offset: usize,
+}
+
+impl Token<'_> {
+    /// The byte range of the token.
+    pub fn range(&self) -> Range<usize> {
+        self.offset..self.offset + self.text.len()
+    }
+}
+
+/// The kind of a `SyntaxNode`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum NodeKind {
+    Protocol,
+    /// The comments describing the node they belong to.
+    Description,
+    Version,
+    Major,
+    Minor,
+    Include,
+    Domain,
+    DependsOn,
+    TypeDef,
+    Enum,
+    Properties,
+    Command,
+    Event,
+    Redirect,
+    Parameters,
+    Returns,
+    Param,
+    Variant,
+    /// A line which isn't valid PDL.
+    Unknown,
+}
+
+/// A child of a `SyntaxNode`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SyntaxElement<'a> {
+    Node(SyntaxNode<'a>),
+    Token(Token<'a>),
+}
+
+impl<'a> SyntaxElement<'a> {
+    /// The source text of the element.
+    pub fn text(&self) -> &'a str {
+        match self {
+            SyntaxElement::Node(node) => node.text,
+            SyntaxElement::Token(token) => token.text,
+        }
+    }
+
+    /// The byte range of the element.
+    pub fn range(&self) -> Range<usize> {
+        match self {
+            SyntaxElement::Node(node) => node.range(),
+            SyntaxElement::Token(token) => token.range(),
+        }
+    }
+}
+
+/// An inner node of the syntax tree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SyntaxNode<'a> {
+    pub kind: NodeKind,
+    /// The source text of the node, with its description and nested lines.
+    pub text: &'a str,
+    /// The byte offset of the node in the source.
+    pub offset: usize,
+    pub children: Vec<SyntaxElement<'a>>,
+}
+
+impl<'a> SyntaxNode<'a> {
+    fn new(kind: NodeKind, offset: usize) -> Self {
+        SyntaxNode {
+            kind,
+            text: "",
+            offset,
+            children: vec![],
+        }
+    }
+
+    /// The byte range of the node.
+    pub fn range(&self) -> Range<usize> {
+        self.offset..self.offset + self.text.len()
+    }
+
+    /// The tokens directly owned by the node, i.e. those of its header line and loose trivia.
+    pub fn tokens(&self) -> impl DoubleEndedIterator<Item = &Token<'a>> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Token(token) => Some(token),
+            _ => None,
+        })
+    }
+
+    /// The nodes directly nested in the node.
+    pub fn nodes(&self) -> impl DoubleEndedIterator<Item = &SyntaxNode<'a>> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            _ => None,
+        })
+    }
+
+    /// All the nodes nested in the node, in source order.
+    pub fn descendants(&self) -> Descendants<'_, 'a> {
+        Descendants {
+            stack: self.nodes().rev().collect(),
+        }
+    }
+
+    /// The description comments of the node.
+    pub fn description(&self) -> Option<&SyntaxNode<'a>> {
+        self.nodes().find(|node| node.kind == NodeKind::Description)
+    }
+
+    /// The token naming the node, e.g. the name of a domain or the id of a type.
+    pub fn name(&self) -> Option<&Token<'a>> {
+        let mut names = self.tokens().filter(|token| token.kind == TokenKind::Name);
+
+        if self.kind == NodeKind::Param {
+            names.next_back()
+        } else {
+            names.next()
+        }
+    }
+
+    /// Find a nested node of the `kind` with the `name`.
+    pub fn child(&self, kind: NodeKind, name: &str) -> Option<&SyntaxNode<'a>> {
+        self.nodes()
+            .find(|node| node.kind == kind && node.name().map(|token| token.text) == Some(name))
+    }
+
+    /// Find the first nested node of the `kind`.
+    pub fn first(&self, kind: NodeKind) -> Option<&SyntaxNode<'a>> {
+        self.nodes().find(|node| node.kind == kind)
+    }
+}
+
+impl fmt::Display for SyntaxNode<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.text)
+    }
+}
+
+/// An iterator over the nodes nested in a `SyntaxNode`, see `SyntaxNode::descendants`.
+pub struct Descendants<'t, 'a> {
+    stack: Vec<&'t SyntaxNode<'a>>,
+}
+
+impl<'t, 'a> Iterator for Descendants<'t, 'a> {
+    type Item = &'t SyntaxNode<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+
+        self.stack.extend(node.nodes().rev());
+
+        Some(node)
+    }
+}
+
+/// A lossless syntax tree of a PDL source, with the edits made to it.
+///
+/// Edits are recorded against byte ranges of the original source, which
+/// are found through the nodes and tokens of the tree, and applied when
+/// the tree is written out with `Display`.
+///
+/// ```
+/// use pdl::{NodeKind, SyntaxTree};
+///
+/// let source = "domain Console\n\n  # Does nothing.\n  command clearMessages\n";
+/// let mut tree = SyntaxTree::parse(source);
+///
+/// let name = tree
+///     .root()
+///     .child(NodeKind::Domain, "Console")
+///     .and_then(|domain| domain.child(NodeKind::Command, "clearMessages"))
+///     .and_then(|command| command.name())
+///     .map(|token| token.range())
+///     .unwrap();
+///
+/// tree.replace(name, "clear");
+///
+/// assert_eq!(
+///     tree.to_string(),
+///     "domain Console\n\n  # Does nothing.\n  command clear\n"
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct SyntaxTree<'a> {
+    source: &'a str,
+    root: SyntaxNode<'a>,
+    edits: Vec<(Range<usize>, String)>,
+}
+
+impl<'a> SyntaxTree<'a> {
+    /// Build the syntax tree of a PDL source.
+    pub fn parse(source: &'a str) -> Self {
+        SyntaxTree {
+            source,
+            root: Builder::new(source, grammar_kinds(source)).build(),
+            edits: vec![],
+        }
+    }
+
+    /// The original source of the tree.
+    pub fn source(&self) -> &'a str {
+        self.source
+    }
+
+    /// The `NodeKind::Protocol` node covering the whole source.
+    pub fn root(&self) -> &SyntaxNode<'a> {
+        &self.root
+    }
+
+    /// Whether any edit was made to the tree.
+    pub fn is_modified(&self) -> bool {
+        !self.edits.is_empty()
+    }
+
+    /// Replace the text in the `range` of the original source.
+    ///
+    /// The `range` must stay inside a single node, i.e. it covers the nodes nested in it either whole or not at all.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `range` is out of the source, crosses the boundary of a node, or overlaps a previous edit.
+    pub fn replace<S: Into<String>>(&mut self, range: Range<usize>, text: S) {
+        assert!(
+            range.start <= range.end && range.end <= self.source.len(),
+            "edit {:?} out of the source",
+            range
+        );
+        assert!(
+            self.source.is_char_boundary(range.start) && self.source.is_char_boundary(range.end),
+            "edit {:?} not on a character boundary",
+            range
+        );
+
+        if let Some(node) = crossed(&self.root, &range) {
+            panic!(
+                "edit {:?} crosses the boundary of {:?} node {:?}",
+                range,
+                node.kind,
+                node.range()
+            );
+        }
+
+        if let Some((edited, _)) = self
+            .edits
+            .iter()
+            .find(|(edited, _)| range.start < edited.end && edited.start < range.end)
+        {
+            panic!("edit {:?} overlaps edit {:?}", range, edited);
+        }
+
+        self.edits.push((range, text.into()));
+    }
+
+    /// Insert text at the `offset` of the original source.
+    ///
+    /// Texts inserted at the same offset are written in the order of insertion.
+    pub fn insert<S: Into<String>>(&mut self, offset: usize, text: S) {
+        self.replace(offset..offset, text)
+    }
+
+    /// Remove the text in the `range` of the original source.
+    pub fn remove(&mut self, range: Range<usize>) {
+        self.replace(range, "")
+    }
+}
+
+impl fmt::Display for SyntaxTree<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut edits = self.edits.iter().collect::<Vec<_>>();
+
+        edits.sort_by_key(|(range, _)| (range.start, range.end));
+
+        let mut pos = 0;
+
+        for (range, text) in edits {
+            f.write_str(&self.source[pos..range.start])?;
+            f.write_str(text)?;
+            pos = range.end;
+        }
+
+        f.write_str(&self.source[pos..])
+    }
+}
+
+/// Find the node whose boundary the `range` crosses, covering part of it.
+fn crossed<'t, 'a>(node: &'t SyntaxNode<'a>, range: &Range<usize>) -> Option<&'t SyntaxNode<'a>> {
+    for child in node.nodes() {
+        let child_range = child.range();
+
+        if child_range.start <= range.start && range.end <= child_range.end {
+            return crossed(child, range);
+        }
+        if range.start < child_range.end
+            && child_range.start < range.end
+            && !(range.start <= child_range.start && child_range.end <= range.end)
+        {
+            return Some(child);
+        }
+    }
+
+    None
+}
+
+/// A line of the source, split into its indentation, content, trailing whitespace and line ending.
+struct Line<'a> {
+    offset: usize,
+    indent: &'a str,
+    content: &'a str,
+    trailing: &'a str,
+    newline: &'a str,
+}
+
+impl<'a> Line<'a> {
+    fn split(source: &'a str, offset: usize) -> (Self, usize) {
+        let rest = &source[offset..];
+        let len = rest.find('\n').map_or(rest.len(), |pos| pos + 1);
+        let line = &rest[..len];
+        let text = line.trim_end_matches(&['\r', '\n'][..]);
+        let newline = &line[text.len()..];
+        let trimmed = text.trim_end();
+        let content = trimmed.trim_start();
+        let indent = &trimmed[..trimmed.len() - content.len()];
+        let trailing = &text[trimmed.len()..];
+
+        (
+            Line {
+                offset,
+                indent,
+                content,
+                trailing,
+                newline,
+            },
+            offset + len,
+        )
+    }
+
+    fn is_blank(&self) -> bool {
+        self.content.is_empty()
+    }
+
+    fn is_comment(&self) -> bool {
+        self.content.starts_with('#')
+    }
+
+    fn content_offset(&self) -> usize {
+        self.offset + self.indent.len()
+    }
+
+    /// The tokens of the line, with the words of the content classified by `classify`.
+    fn tokens<F>(&self, classify: F) -> Vec<Token<'a>>
+    where
+        F: FnOnce(&[&str]) -> Vec<TokenKind>,
+    {
+        let mut tokens = vec![];
+        let mut offset = self.offset;
+        let mut push = |kind, text: &'a str| {
+            if !text.is_empty() {
+                tokens.push(Token { kind, text, offset });
+                offset += text.len();
+            }
+        };
+
+        push(TokenKind::Whitespace, self.indent);
+
+        if self.is_comment() {
+            push(TokenKind::Comment, self.content);
+        } else {
+            let words = self.content.split_whitespace().collect::<Vec<_>>();
+            let kinds = classify(&words);
+            let mut rest = self.content;
+
+            for (word, kind) in words.iter().zip(kinds) {
+                let start = rest.find(word).unwrap_or_default();
+
+                push(TokenKind::Whitespace, &rest[..start]);
+                push(kind, word);
+                rest = &rest[start + word.len()..];
+            }
+        }
+
+        push(TokenKind::Whitespace, self.trailing);
+        push(TokenKind::Newline, self.newline);
+
+        tokens
+    }
+}
+
+/// The kinds of the lines the grammar parses as a domain, `depends on`, item, redirect,
+/// parameter or variant, by the offset of their content.
+fn grammar_kinds(source: &str) -> HashMap<usize, NodeKind> {
+    fn params(kinds: &mut HashMap<usize, NodeKind>, params: &[Param]) {
+        for param in params {
+            kinds.insert(param.span.start, NodeKind::Param);
+
+            let mut ty = &param.ty;
+
+            while let Type::ArrayOf(ref item) = ty {
+                ty = item;
+            }
+            if let Type::Enum(ref variants) = ty {
+                variants_of(kinds, variants);
+            }
+        }
+    }
+
+    fn variants_of(kinds: &mut HashMap<usize, NodeKind>, variants: &[Variant]) {
+        for variant in variants {
+            kinds.insert(variant.span.start, NodeKind::Variant);
+        }
+    }
+
+    let (protocol, _) = super::parse_recovering(source);
+    let mut kinds = HashMap::new();
+
+    for domain in &protocol.domains {
+        kinds.insert(domain.span.start, NodeKind::Domain);
+
+        for dep in &domain.dependencies {
+            kinds.insert(dep.span.start, NodeKind::DependsOn);
+        }
+        for ty in &domain.types {
+            kinds.insert(ty.span.start, NodeKind::TypeDef);
+
+            match ty.item {
+                Some(Item::Enum(ref variants)) => variants_of(&mut kinds, variants),
+                Some(Item::Properties(ref props)) => params(&mut kinds, props),
+                None => {}
+            }
+        }
+        for cmd in &domain.commands {
+            kinds.insert(cmd.span.start, NodeKind::Command);
+
+            if let Some(ref redirect) = cmd.redirect {
+                kinds.insert(redirect.span.start, NodeKind::Redirect);
+            }
+
+            params(&mut kinds, &cmd.parameters);
+            params(&mut kinds, &cmd.returns);
+        }
+        for evt in &domain.events {
+            kinds.insert(evt.span.start, NodeKind::Event);
+
+            params(&mut kinds, &evt.parameters);
+        }
+    }
+
+    kinds
+}
+
+/// Classify a line by its words, unless the grammar `parsed` it as a node of some kind.
+fn classify(words: &[&str], parsed: Option<NodeKind>) -> (NodeKind, Vec<TokenKind>) {
+    use self::TokenKind::*;
+
+    let modifiers = words
+        .iter()
+        .take_while(|word| super::is_modifier(word))
+        .count();
+    let rest = &words[modifiers..];
+    // the grammar has the last word on the lines it parses,
+    // the others are either keyword lines or rejected by the grammar
+    let kind = match parsed {
+        Some(kind) => kind,
+        None => match rest {
+            _ if words.first() == Some(&"major") => NodeKind::Major,
+            _ if words.first() == Some(&"minor") => NodeKind::Minor,
+            ["version", ..] => NodeKind::Version,
+            ["include", ..] => NodeKind::Include,
+            ["enum"] => NodeKind::Enum,
+            ["properties"] => NodeKind::Properties,
+            ["parameters"] => NodeKind::Parameters,
+            ["returns"] => NodeKind::Returns,
+            _ => NodeKind::Unknown,
+        },
+    };
+
+    let keywords = match kind {
+        NodeKind::Variant | NodeKind::Unknown => 0,
+        // `[modifiers] [array of] type name`
+        NodeKind::Param => words.len().saturating_sub(2),
+        NodeKind::Major | NodeKind::Minor => 1,
+        NodeKind::DependsOn => modifiers + 2,
+        NodeKind::Version
+        | NodeKind::Enum
+        | NodeKind::Properties
+        | NodeKind::Parameters
+        | NodeKind::Returns => words.len(),
+        _ => modifiers + 1,
+    };
+
+    let tokens = words
+        .iter()
+        .enumerate()
+        .map(|(i, _)| match kind {
+            _ if i < keywords => Keyword,
+            NodeKind::Major | NodeKind::Minor => Number,
+            // `type id extends [array of] type`
+            NodeKind::TypeDef if i > keywords && i + 1 < words.len() => Keyword,
+            _ => Name,
+        })
+        .collect();
+
+    (kind, tokens)
+}
+
+/// The kinds of nodes a node of the `kind` can be nested in, if it is restricted by the grammar.
+fn parents(kind: NodeKind) -> &'static [NodeKind] {
+    match kind {
+        NodeKind::Major | NodeKind::Minor => &[NodeKind::Version],
+        NodeKind::Version | NodeKind::Include | NodeKind::Domain => &[NodeKind::Protocol],
+        NodeKind::DependsOn | NodeKind::TypeDef | NodeKind::Command | NodeKind::Event => {
+            &[NodeKind::Domain]
+        }
+        NodeKind::Enum | NodeKind::Properties => &[NodeKind::TypeDef],
+        NodeKind::Parameters => &[NodeKind::Command, NodeKind::Event],
+        NodeKind::Returns | NodeKind::Redirect => &[NodeKind::Command],
+        NodeKind::Param => &[
+            NodeKind::Properties,
+            NodeKind::Parameters,
+            NodeKind::Returns,
+        ],
+        NodeKind::Variant => &[NodeKind::Enum, NodeKind::Param],
+        _ => &[],
+    }
+}
+
+/// Build a syntax tree by following the indentation of the lines.
+struct Builder<'a> {
+    source: &'a str,
+    /// The open nodes, with the indentation of their header line.
+    stack: Vec<(usize, SyntaxNode<'a>)>,
+    /// The blank and comment lines not yet attached to a node.
+    pending: Vec<Line<'a>>,
+    /// The kinds of the lines parsed by the grammar, by the offset of their content.
+    parsed: HashMap<usize, NodeKind>,
+}
+
+impl<'a> Builder<'a> {
+    fn new(source: &'a str, parsed: HashMap<usize, NodeKind>) -> Self {
+        Builder {
+            source,
+            stack: vec![(0, SyntaxNode::new(NodeKind::Protocol, 0))],
+            pending: vec![],
+            parsed,
+        }
+    }
+
+    fn build(mut self) -> SyntaxNode<'a> {
+        let mut offset = 0;
+
+        if self.source.starts_with('\u{feff}') {
+            offset = '\u{feff}'.len_utf8();
+            self.push(SyntaxElement::Token(Token {
+                kind: TokenKind::Bom,
+                text: &self.source[..offset],
+                offset: 0,
+            }));
+        }
+
+        while offset < self.source.len() {
+            let (line, next) = Line::split(self.source, offset);
+
+            if line.is_blank() || line.is_comment() {
+                self.pending.push(line);
+            } else {
+                self.line(line);
+            }
+
+            offset = next;
+        }
+
+        self.close(0);
+        self.trivia(self.pending.len());
+
+        let (_, mut root) = self.stack.pop().unwrap();
+
+        self.finish(&mut root);
+
+        root
+    }
+
+    fn line(&mut self, line: Line<'a>) {
+        let indent = line.indent.len();
+        let words = line.content.split_whitespace().collect::<Vec<_>>();
+        let (mut kind, mut kinds) =
+            classify(&words, self.parsed.get(&line.content_offset()).copied());
+        let parents = parents(kind);
+
+        // the grammar doesn't follow the indentation, e.g. a parameter may be less indented than
+        // the one before it, so a node closes the nodes up to the innermost one it can be nested
+        // in, and only the lines the grammar rejects are nested by their indentation
+        if self
+            .stack
+            .iter()
+            .any(|(_, node)| parents.contains(&node.kind))
+        {
+            while !parents.contains(&self.stack.last().unwrap().1.kind) {
+                self.close_innermost();
+            }
+        } else {
+            if !parents.is_empty() {
+                // a keyword out of place, e.g. `returns` of a malformed command
+                kind = NodeKind::Unknown;
+                kinds = vec![TokenKind::Name; words.len()];
+            }
+
+            self.close(indent);
+        }
+
+        // comments right before the line describe it, others are loose trivia
+        let described = self
+            .pending
+            .iter()
+            .rev()
+            .take_while(|line| line.is_comment())
+            .count();
+
+        self.trivia(self.pending.len() - described);
+
+        let mut node = SyntaxNode::new(kind, line.offset);
+
+        if described > 0 {
+            let mut description = SyntaxNode::new(NodeKind::Description, self.pending[0].offset);
+
+            for comment in self.pending.drain(..) {
+                description.children.extend(
+                    comment
+                        .tokens(|_| vec![])
+                        .into_iter()
+                        .map(SyntaxElement::Token),
+                );
+            }
+
+            self.finish(&mut description);
+            node.offset = description.offset;
+            node.children.push(SyntaxElement::Node(description));
+        }
+
+        node.children
+            .extend(line.tokens(|_| kinds).into_iter().map(SyntaxElement::Token));
+
+        trace!("{:?} at {}", kind, line.content_offset());
+
+        self.stack.push((indent, node));
+    }
+
+    /// Close the open nodes which are not less indented than `indent`.
+    fn close(&mut self, indent: usize) {
+        while self.stack.len() > 1 && self.stack.last().unwrap().0 >= indent {
+            self.close_innermost();
+        }
+    }
+
+    fn close_innermost(&mut self) {
+        let (_, mut node) = self.stack.pop().unwrap();
+
+        self.finish(&mut node);
+        self.push(SyntaxElement::Node(node));
+    }
+
+    /// Attach the first `n` pending lines to the innermost open node.
+    fn trivia(&mut self, n: usize) {
+        let lines = self.pending.drain(..n).collect::<Vec<_>>();
+
+        for line in lines {
+            for token in line.tokens(|_| vec![]) {
+                self.push(SyntaxElement::Token(token));
+            }
+        }
+    }
+
+    fn push(&mut self, element: SyntaxElement<'a>) {
+        self.stack.last_mut().unwrap().1.children.push(element);
+    }
+
+    fn finish(&self, node: &mut SyntaxNode<'a>) {
+        let end = node
+            .children
+            .last()
+            .map_or(node.offset, |child| child.range().end);
+
+        node.text = &self.source[node.offset..end];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Protocol;
+
+    const SOURCE: &str = "\u{feff}# Copyright\r\n\r\nversion\r\n  major 1\r\n  minor 3\r\n\r\n# The Console domain.\n\n# Console domain defines methods and events for interaction with the JavaScript console.\ndeprecated internal domain Console   \n  depends on Runtime\n\n  # Console message.\n  type ConsoleMessage extends object\n    properties\n      # Message source.\n      enum source\n        xml\n        javascript\n      # Message text.\n      string text\n      optional array of integer lines\n\n\tcommand clearMessages\n\n  # Issued when new console message is added.\n  event messageAdded\n    parameters\n      # Console message that has been added.\n      ConsoleMessage message\n\n# trailing\n";
+
+    fn kinds<'a>(node: &SyntaxNode<'a>) -> Vec<(NodeKind, Option<&'a str>)> {
+        node.descendants()
+            .filter(|node| node.kind != NodeKind::Description)
+            .map(|node| (node.kind, node.name().map(|token| token.text)))
+            .collect()
+    }
+
+    #[test]
+    fn lossless() {
+        let tree = SyntaxTree::parse(SOURCE);
+
+        assert_eq!(tree.to_string(), SOURCE);
+        assert_eq!(tree.root().text, SOURCE);
+
+        fn concat(node: &SyntaxNode, text: &mut String) {
+            for child in &node.children {
+                match child {
+                    SyntaxElement::Node(node) => concat(node, text),
+                    SyntaxElement::Token(token) => text.push_str(token.text),
+                }
+            }
+        }
+
+        let mut text = String::new();
+
+        concat(tree.root(), &mut text);
+
+        assert_eq!(text, SOURCE);
+    }
+
+    #[test]
+    fn structure() {
+        let tree = SyntaxTree::parse(SOURCE);
+
+        assert_eq!(
+            kinds(tree.root()),
+            vec![
+                (NodeKind::Version, None),
+                (NodeKind::Major, None),
+                (NodeKind::Minor, None),
+                (NodeKind::Domain, Some("Console")),
+                (NodeKind::DependsOn, Some("Runtime")),
+                (NodeKind::TypeDef, Some("ConsoleMessage")),
+                (NodeKind::Properties, None),
+                (NodeKind::Param, Some("source")),
+                (NodeKind::Variant, Some("xml")),
+                (NodeKind::Variant, Some("javascript")),
+                (NodeKind::Param, Some("text")),
+                (NodeKind::Param, Some("lines")),
+                (NodeKind::Command, Some("clearMessages")),
+                (NodeKind::Event, Some("messageAdded")),
+                (NodeKind::Parameters, None),
+                (NodeKind::Param, Some("message")),
+            ]
+        );
+
+        let domain = tree.root().first(NodeKind::Domain).unwrap();
+
+        assert_eq!(
+            domain.description().unwrap().text,
+            "# Console domain defines methods and events for interaction with the JavaScript console.\n"
+        );
+        assert!(domain.text.ends_with("ConsoleMessage message\n"));
+
+        let ty = domain.child(NodeKind::TypeDef, "ConsoleMessage").unwrap();
+
+        assert_eq!(
+            ty.tokens()
+                .filter(|token| token.kind != TokenKind::Whitespace)
+                .map(|token| (token.kind, token.text))
+                .collect::<Vec<_>>(),
+            vec![
+                (TokenKind::Keyword, "type"),
+                (TokenKind::Name, "ConsoleMessage"),
+                (TokenKind::Keyword, "extends"),
+                (TokenKind::Name, "object"),
+                (TokenKind::Newline, "\n"),
+            ]
+        );
+    }
+
+    #[test]
+    fn edit() {
+        let mut tree = SyntaxTree::parse(SOURCE);
+        let domain = tree.root().child(NodeKind::Domain, "Console").unwrap();
+        let name = domain.name().unwrap().range();
+        let event = domain
+            .child(NodeKind::Event, "messageAdded")
+            .unwrap()
+            .range();
+        let text = domain
+            .child(NodeKind::TypeDef, "ConsoleMessage")
+            .and_then(|ty| ty.first(NodeKind::Properties))
+            .and_then(|props| props.child(NodeKind::Param, "text"))
+            .unwrap()
+            .offset;
+
+        tree.replace(name, "Log");
+        tree.remove(event);
+        tree.insert(text, "      integer line\n");
+
+        assert!(tree.is_modified());
+        assert_eq!(
+            tree.to_string(),
+            SOURCE
+                .replace("Console   \n", "Log   \n")
+                .replace(
+                    "\n      # Message text.\n",
+                    "\n      integer line\n      # Message text.\n"
+                )
+                .replace(
+                    "  # Issued when new console message is added.\n  event messageAdded\n    parameters\n      # Console message that has been added.\n      ConsoleMessage message\n",
+                    ""
+                )
+        );
+    }
+
+    /// The named nodes of the `protocol`, in the order they appear in the source.
+    fn nodes<'a>(protocol: &'a Protocol) -> Vec<(NodeKind, Option<&'a str>)> {
+        fn params<'a>(nodes: &mut Vec<(usize, NodeKind, &'a str)>, params: &'a [Param]) {
+            for param in params {
+                nodes.push((param.span.start, NodeKind::Param, &param.name));
+
+                let mut ty = &param.ty;
+
+                while let Type::ArrayOf(ref item) = ty {
+                    ty = item;
+                }
+                if let Type::Enum(ref variants) = ty {
+                    variants_of(nodes, variants);
+                }
+            }
+        }
+
+        fn variants_of<'a>(nodes: &mut Vec<(usize, NodeKind, &'a str)>, variants: &'a [Variant]) {
+            for variant in variants {
+                nodes.push((variant.span.start, NodeKind::Variant, &variant.name));
+            }
+        }
+
+        let mut nodes = vec![];
+
+        for domain in &protocol.domains {
+            nodes.push((domain.span.start, NodeKind::Domain, domain.name.as_ref()));
+
+            for dep in &domain.dependencies {
+                nodes.push((dep.span.start, NodeKind::DependsOn, &dep.name));
+            }
+            for ty in &domain.types {
+                nodes.push((ty.span.start, NodeKind::TypeDef, &ty.id));
+
+                match ty.item {
+                    Some(Item::Enum(ref variants)) => variants_of(&mut nodes, variants),
+                    Some(Item::Properties(ref props)) => params(&mut nodes, props),
+                    None => {}
+                }
+            }
+            for cmd in &domain.commands {
+                nodes.push((cmd.span.start, NodeKind::Command, &cmd.name));
+
+                if let Some(ref redirect) = cmd.redirect {
+                    nodes.push((redirect.span.start, NodeKind::Redirect, &redirect.to));
+                }
+
+                params(&mut nodes, &cmd.parameters);
+                params(&mut nodes, &cmd.returns);
+            }
+            for evt in &domain.events {
+                nodes.push((evt.span.start, NodeKind::Event, &evt.name));
+
+                params(&mut nodes, &evt.parameters);
+            }
+        }
+
+        nodes.sort_by_key(|&(start, _, _)| start);
+        nodes
+            .into_iter()
+            .map(|(_, kind, name)| (kind, Some(name)))
+            .collect()
+    }
+
+    /// The named nodes of the `tree`, leaving out keyword lines and lines the grammar rejects.
+    fn named<'a>(tree: &SyntaxTree<'a>) -> Vec<(NodeKind, Option<&'a str>)> {
+        kinds(tree.root())
+            .into_iter()
+            .filter(|(kind, _)| {
+                !matches!(
+                    kind,
+                    NodeKind::Version
+                        | NodeKind::Major
+                        | NodeKind::Minor
+                        | NodeKind::Enum
+                        | NodeKind::Properties
+                        | NodeKind::Parameters
+                        | NodeKind::Returns
+                        | NodeKind::Unknown
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn same_as_grammar() {
+        for source in &[
+            crate::parse::tests::SNIPPET,
+            include_str!("../../tests/data/protocol.pdl"),
+        ] {
+            let tree = SyntaxTree::parse(source);
+            let protocol = crate::parse_str(source).unwrap();
+
+            assert_eq!(named(&tree), nodes(&protocol));
+        }
+    }
+
+    const LOOSE: &str = r#"version
+  major 1
+  minor 3
+
+domain Page
+  event loaded
+    parameters
+      string url
+      type FrameId extends string
+
+  command navigate
+    parameters
+      string url
+        optional string referrer
+      returns
+        FrameId frameId
+
+  command reload
+    parameters
+      enum mode
+        soft
+          hard
+  comand stop now
+  type
+
+	event stopped
+    parameters
+      string
+      integer code
+"#;
+
+    #[test]
+    fn same_as_recovering_grammar() {
+        let tree = SyntaxTree::parse(LOOSE);
+        let (protocol, diagnostics) = crate::parse_recovering(LOOSE);
+
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(named(&tree), nodes(&protocol));
+        assert_eq!(tree.to_string(), LOOSE);
+
+        assert_eq!(
+            kinds(tree.root()),
+            vec![
+                (NodeKind::Version, None),
+                (NodeKind::Major, None),
+                (NodeKind::Minor, None),
+                (NodeKind::Domain, Some("Page")),
+                (NodeKind::Event, Some("loaded")),
+                (NodeKind::Parameters, None),
+                (NodeKind::Param, Some("url")),
+                (NodeKind::TypeDef, Some("FrameId")),
+                (NodeKind::Command, Some("navigate")),
+                (NodeKind::Parameters, None),
+                (NodeKind::Param, Some("url")),
+                (NodeKind::Param, Some("referrer")),
+                (NodeKind::Returns, None),
+                (NodeKind::Param, Some("frameId")),
+                (NodeKind::Command, Some("reload")),
+                (NodeKind::Parameters, None),
+                (NodeKind::Param, Some("mode")),
+                (NodeKind::Variant, Some("soft")),
+                (NodeKind::Variant, Some("hard")),
+                (NodeKind::Param, Some("now")),
+                (NodeKind::Unknown, Some("type")),
+                (NodeKind::Event, Some("stopped")),
+                (NodeKind::Parameters, None),
+                (NodeKind::Unknown, Some("string")),
+                (NodeKind::Param, Some("code")),
+            ]
+        );
+
+        let domain = tree.root().child(NodeKind::Domain, "Page").unwrap();
+
+        assert!(domain.child(NodeKind::TypeDef, "FrameId").is_some());
+        assert!(domain
+            .child(NodeKind::Command, "reload")
+            .and_then(|cmd| cmd.first(NodeKind::Parameters))
+            .and_then(|params| params.child(NodeKind::Param, "now"))
+            .is_some());
+        assert!(domain
+            .child(NodeKind::Command, "navigate")
+            .and_then(|cmd| cmd.first(NodeKind::Returns))
+            .is_some());
+    }
+
+    #[test]
+    #[should_panic(expected = "crosses the boundary of Param node")]
+    fn edit_across_nodes() {
+        let mut tree = SyntaxTree::parse(SOURCE);
+        let props = tree
+            .root()
+            .child(NodeKind::Domain, "Console")
+            .and_then(|domain| domain.child(NodeKind::TypeDef, "ConsoleMessage"))
+            .and_then(|ty| ty.first(NodeKind::Properties))
+            .unwrap();
+        let text = props.child(NodeKind::Param, "text").unwrap().range();
+        let lines = props.child(NodeKind::Param, "lines").unwrap().range();
+
+        tree.remove(text.start + 2..lines.start + 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "overlaps")]
+    fn overlapping_edits() {
+        let mut tree = SyntaxTree::parse(SOURCE);
+        let domain = tree.root().first(NodeKind::Domain).unwrap().range();
+
+        tree.remove(domain.clone());
+        tree.insert(domain.start + 1, "x");
+    }
+}
@@ -0,0 +1,305 @@
+use std::error::Error as StdError;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use nom::error::ErrorKind;
+
+use crate::{Diagnostic, Span};
+
+/// A token or construct the grammar was looking for when parsing failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Expected {
+    Token(&'static str),
+    Char(char),
+    Description(&'static str),
+}
+
+impl fmt::Display for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expected::Token(token) => write!(f, "`{}`", token),
+            Expected::Char('\n') => f.write_str("end of line"),
+            Expected::Char(c) => write!(f, "{:?}", c),
+            Expected::Description(s) => f.write_str(s),
+        }
+    }
+}
+
+/// The error type used internally by the nom grammar.
+///
+/// It keeps the furthest position reached by the grammar, what was expected there,
+/// and the innermost grammar rule that was being parsed.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Error<'a> {
+    pub input: &'a str,
+    pub expected: Vec<Expected>,
+    pub rule: Option<&'static str>,
+    /// A modifier given twice.
+    pub duplicate: Option<&'a str>,
+}
+
+impl<'a> Error<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Error {
+            input,
+            expected: vec![],
+            rule: None,
+            duplicate: None,
+        }
+    }
+
+    pub fn expected(input: &'a str, expected: Expected) -> Self {
+        Error {
+            expected: vec![expected],
+            ..Error::new(input)
+        }
+    }
+
+    pub fn duplicate(input: &'a str, modifier: &'a str) -> Self {
+        Error {
+            duplicate: Some(modifier),
+            ..Error::new(input)
+        }
+    }
+}
+
+impl<'a> nom::error::ParseError<&'a str> for Error<'a> {
+    fn from_error_kind(input: &'a str, _kind: ErrorKind) -> Self {
+        Error::new(input)
+    }
+
+    fn append(_input: &'a str, _kind: ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    fn from_char(input: &'a str, c: char) -> Self {
+        Error::expected(input, Expected::Char(c))
+    }
+
+    fn or(mut self, mut other: Self) -> Self {
+        // the alternative that went further wins, ties merge their expectations
+        if self.input.len() < other.input.len() {
+            self
+        } else if self.input.len() > other.input.len() {
+            other
+        } else {
+            for expected in other.expected.drain(..) {
+                if !self.expected.contains(&expected) {
+                    self.expected.push(expected);
+                }
+            }
+
+            if self.rule != other.rule {
+                self.rule = None;
+            }
+
+            self
+        }
+    }
+
+    fn add_context(_input: &'a str, rule: &'static str, mut other: Self) -> Self {
+        if other.rule.is_none() {
+            other.rule = Some(rule);
+        }
+
+        other
+    }
+}
+
+/// An error which occurred while parsing a PDL file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    inner: Box<Inner>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Inner {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+    pub source_line: String,
+    pub rule: Option<&'static str>,
+    pub expected: Vec<String>,
+    pub duplicate: Option<String>,
+    pub path: Option<PathBuf>,
+    pub included_from: Vec<IncludedFrom>,
+}
+
+/// The location of an `include` directive.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IncludedFrom {
+    /// The file containing the `include` directive.
+    pub path: PathBuf,
+    /// The 1-based line number of the `include` directive.
+    pub line: usize,
+}
+
+impl fmt::Display for IncludedFrom {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.path.display(), self.line)
+    }
+}
+
+impl ParseError {
+    pub(crate) fn new(source: &str, err: Error) -> ParseError {
+        let mut error = ParseError::at(source, source.len() - err.input.len());
+
+        error.inner.rule = err.rule;
+        error.inner.expected = err.expected.iter().map(|e| e.to_string()).collect();
+        error.inner.duplicate = err.duplicate.map(str::to_owned);
+        error
+    }
+
+    pub(crate) fn from_nom(source: &str, err: nom::Err<Error>) -> ParseError {
+        match err {
+            nom::Err::Error(err) | nom::Err::Failure(err) => ParseError::new(source, err),
+            nom::Err::Incomplete(_) => ParseError::new(
+                source,
+                Error::expected(&source[source.len()..], Expected::Description("more input")),
+            ),
+        }
+    }
+
+    pub(crate) fn at(source: &str, offset: usize) -> ParseError {
+        let line_start = source[..offset].rfind('\n').map_or(0, |pos| pos + 1);
+        let line_end = source[offset..]
+            .find('\n')
+            .map_or(source.len(), |pos| offset + pos);
+
+        ParseError {
+            inner: Box::new(Inner {
+                offset,
+                line: line_at(source, offset),
+                column: source[line_start..offset].chars().count() + 1,
+                source_line: source[line_start..line_end]
+                    .trim_end_matches('\r')
+                    .to_owned(),
+                rule: None,
+                expected: vec![],
+                duplicate: None,
+                path: None,
+                included_from: vec![],
+            }),
+        }
+    }
+
+    pub(crate) fn inner_mut(&mut self) -> &mut Inner {
+        &mut self.inner
+    }
+
+    /// The byte offset of the error in the input.
+    pub fn offset(&self) -> usize {
+        self.inner.offset
+    }
+
+    /// The 1-based line number of the error.
+    pub fn line(&self) -> usize {
+        self.inner.line
+    }
+
+    /// The 1-based column number of the error, counted in characters.
+    pub fn column(&self) -> usize {
+        self.inner.column
+    }
+
+    /// The text of the offending line, without the line ending.
+    pub fn source_line(&self) -> &str {
+        &self.inner.source_line
+    }
+
+    /// The grammar rule that failed, e.g. `domain`, `type_def` or `param`.
+    pub fn rule(&self) -> Option<&'static str> {
+        self.inner.rule
+    }
+
+    /// What was expected at the error position.
+    pub fn expected(&self) -> &[String] {
+        &self.inner.expected
+    }
+
+    /// The file in which the error occurred, if the input was loaded from one.
+    pub fn path(&self) -> Option<&Path> {
+        self.inner.path.as_deref()
+    }
+
+    /// The chain of `include` directives that led to the file, innermost first.
+    pub fn included_from(&self) -> &[IncludedFrom] {
+        &self.inner.included_from
+    }
+
+    /// The modifier given twice, if that's what went wrong.
+    pub fn duplicate(&self) -> Option<&str> {
+        self.inner.duplicate.as_deref()
+    }
+
+    /// Describe what went wrong, without the position.
+    pub fn message(&self) -> String {
+        let mut msg = match self.expected() {
+            _ if self.duplicate().is_some() => {
+                format!("duplicate modifier `{}`", self.duplicate().unwrap())
+            }
+            [] => "unexpected input".to_owned(),
+            [expected] => format!("expected {}", expected),
+            [init @ .., last] => format!("expected {} or {}", init.join(", "), last),
+        };
+
+        if let Some(rule) = self.rule() {
+            msg.push_str(" in ");
+            msg.push_str(rule);
+        }
+
+        msg
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let lineno = self.line().to_string();
+        let gutter = " ".repeat(lineno.len());
+        let padding = self
+            .source_line()
+            .chars()
+            .take(self.column() - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect::<String>();
+
+        for include in self.included_from() {
+            writeln!(f, "in file included from {}", include)?;
+        }
+        if let Some(path) = self.path() {
+            write!(f, "{}:", path.display())?;
+        }
+        writeln!(f, "{}:{}: {}", self.line(), self.column(), self.message())?;
+        writeln!(f, "{} |", gutter)?;
+        writeln!(f, "{} | {}", lineno, self.source_line())?;
+        write!(f, "{} | {}^", gutter, padding)
+    }
+}
+
+impl StdError for ParseError {}
+
+/// The 1-based line number of `offset` in `source`.
+pub(crate) fn line_at(source: &str, offset: usize) -> usize {
+    source[..offset].matches('\n').count() + 1
+}
+
+impl From<ParseError> for Diagnostic {
+    fn from(err: ParseError) -> Diagnostic {
+        let source_line = err.source_line();
+        let line_offset = source_line
+            .char_indices()
+            .nth(err.column() - 1)
+            .map_or(source_line.len(), |(pos, _)| pos);
+
+        Diagnostic::error(
+            "syntax",
+            err.message(),
+            Span {
+                start: err.offset(),
+                end: err.offset() + source_line.len() - line_offset,
+                line: err.line(),
+                column: err.column(),
+            },
+        )
+    }
+}
@@ -0,0 +1,633 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::error::Error as StdError;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::iter::FromIterator;
+use std::path::{Component, Path, PathBuf};
+
+use nom::Offset;
+
+use super::error::line_at;
+use super::*;
+
+/// A source of PDL files used to resolve `include` directives.
+pub trait Loader {
+    /// Resolve the `path` of an `include` directive found in the file `base`.
+    ///
+    /// The default implementation resolves it relative to the directory of `base`.
+    fn resolve(&self, base: &Path, path: &str) -> PathBuf {
+        normalize(&base.parent().unwrap_or_else(|| Path::new("")).join(path))
+    }
+
+    /// Load the content of the file at `path`.
+    fn load(&self, path: &Path) -> io::Result<String>;
+}
+
+/// Load PDL files from the filesystem.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FileLoader;
+
+impl Loader for FileLoader {
+    fn load(&self, path: &Path) -> io::Result<String> {
+        fs::read_to_string(path)
+    }
+}
+
+/// Load PDL files from memory, mostly useful for tests.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryLoader {
+    files: HashMap<PathBuf, String>,
+}
+
+impl MemoryLoader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a file, returning the previous content of the same path.
+    pub fn insert<P: AsRef<Path>, S: Into<String>>(
+        &mut self,
+        path: P,
+        content: S,
+    ) -> Option<String> {
+        self.files.insert(normalize(path.as_ref()), content.into())
+    }
+}
+
+impl<P: AsRef<Path>, S: Into<String>> FromIterator<(P, S)> for MemoryLoader {
+    fn from_iter<T: IntoIterator<Item = (P, S)>>(iter: T) -> Self {
+        let mut loader = MemoryLoader::new();
+
+        for (path, content) in iter {
+            loader.insert(path, content);
+        }
+
+        loader
+    }
+}
+
+impl Loader for MemoryLoader {
+    fn load(&self, path: &Path) -> io::Result<String> {
+        self.files.get(path).cloned().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} not found", path.display()),
+            )
+        })
+    }
+}
+
+/// Parse a PDL file, resolving its `include` directives relative to the directory of the file.
+///
+/// The returned `Protocol` owns its content, and errors mention the path of the file they occurred in.
+pub fn parse_file<P: AsRef<Path>>(path: P) -> Result<Protocol<'static>, LoadError> {
+    let sources = Sources::load(&FileLoader, path)?;
+    let protocol = sources.parse()?;
+
+    Ok(protocol.into_owned())
+}
+
+/// The path standing for the content read by `parse_reader` in errors.
+const READER_PATH: &str = "<reader>";
+
+/// Parse PDL from a reader, resolving its `include` directives relative to the current directory.
+///
+/// The returned `Protocol` owns its content.
+pub fn parse_reader<R: Read>(mut reader: R) -> Result<Protocol<'static>, LoadError> {
+    let mut text = String::new();
+
+    reader
+        .read_to_string(&mut text)
+        .map_err(|error| LoadError::Io {
+            path: READER_PATH.into(),
+            included_from: vec![],
+            error,
+        })?;
+
+    let sources = Sources::load(&ReaderLoader(text), READER_PATH)?;
+    let protocol = sources.parse()?;
+
+    Ok(protocol.into_owned())
+}
+
+/// Serve the content read by `parse_reader`, and the files it includes from the filesystem.
+struct ReaderLoader(String);
+
+impl Loader for ReaderLoader {
+    fn load(&self, path: &Path) -> io::Result<String> {
+        if path == Path::new(READER_PATH) {
+            Ok(self.0.clone())
+        } else {
+            FileLoader.load(path)
+        }
+    }
+}
+
+/// Lexically normalize a path, removing `.` and resolving `..` components.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(
+                    normalized.components().next_back(),
+                    Some(Component::Normal(_))
+                ) =>
+            {
+                normalized.pop();
+            }
+            _ => normalized.push(component),
+        }
+    }
+
+    normalized
+}
+
+/// An error which occurred while loading or parsing a set of PDL files.
+#[derive(Debug)]
+pub enum LoadError {
+    /// A file could not be loaded.
+    Io {
+        path: PathBuf,
+        included_from: Vec<IncludedFrom>,
+        error: io::Error,
+    },
+    /// A file includes itself, directly or indirectly.
+    Cycle {
+        path: PathBuf,
+        included_from: Vec<IncludedFrom>,
+    },
+    /// A file could not be parsed.
+    Parse(ParseError),
+}
+
+impl From<ParseError> for LoadError {
+    fn from(err: ParseError) -> Self {
+        LoadError::Parse(err)
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Io {
+                path,
+                included_from,
+                error,
+            } => {
+                for include in included_from {
+                    writeln!(f, "in file included from {}", include)?;
+                }
+                write!(f, "fail to load {}, {}", path.display(), error)
+            }
+            LoadError::Cycle {
+                path,
+                included_from,
+            } => {
+                for include in included_from {
+                    writeln!(f, "in file included from {}", include)?;
+                }
+                write!(f, "{} includes itself", path.display())
+            }
+            LoadError::Parse(err) => err.fmt(f),
+        }
+    }
+}
+
+impl StdError for LoadError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            LoadError::Io { error, .. } => Some(error),
+            LoadError::Cycle { .. } => None,
+            LoadError::Parse(err) => Some(err),
+        }
+    }
+}
+
+/// A root PDL file together with every file it includes.
+///
+/// The files are loaded up front, so the parsed `Protocol` can borrow from them.
+#[derive(Clone, Debug)]
+pub struct Sources {
+    root: PathBuf,
+    files: HashMap<PathBuf, Source>,
+}
+
+#[derive(Clone, Debug)]
+struct Source {
+    text: String,
+    /// The resolved path of the `include` directive on each line.
+    includes: BTreeMap<usize, PathBuf>,
+}
+
+impl Sources {
+    /// Load the `root` file and, recursively, every file it includes.
+    pub fn load<L: Loader, P: AsRef<Path>>(loader: &L, root: P) -> Result<Sources, LoadError> {
+        let mut sources = Sources {
+            root: normalize(root.as_ref()),
+            files: HashMap::new(),
+        };
+
+        sources.load_file(loader, sources.root.clone(), &mut vec![])?;
+
+        Ok(sources)
+    }
+
+    fn load_file<L: Loader>(
+        &mut self,
+        loader: &L,
+        path: PathBuf,
+        stack: &mut Vec<IncludedFrom>,
+    ) -> Result<(), LoadError> {
+        if stack.iter().any(|include| include.path == path) {
+            return Err(LoadError::Cycle {
+                path,
+                included_from: stack.iter().rev().cloned().collect(),
+            });
+        }
+        if self.files.contains_key(&path) {
+            return Ok(());
+        }
+
+        let text = loader.load(&path).map_err(|error| LoadError::Io {
+            path: path.clone(),
+            included_from: stack.iter().rev().cloned().collect(),
+            error,
+        })?;
+
+        let includes = text
+            .split_inclusive('\n')
+            .enumerate()
+            .filter_map(|(lineno, line)| {
+                include(line.trim_start_matches('\u{feff}'))
+                    .ok()
+                    .map(|(_, name)| (lineno + 1, loader.resolve(&path, name)))
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        for (&line, target) in &includes {
+            stack.push(IncludedFrom {
+                path: path.clone(),
+                line,
+            });
+            self.load_file(loader, target.clone(), stack)?;
+            stack.pop();
+        }
+
+        self.files.insert(path, Source { text, includes });
+
+        Ok(())
+    }
+
+    /// The path of the root file.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The content of a loaded file.
+    pub fn get<P: AsRef<Path>>(&self, path: P) -> Option<&str> {
+        self.files
+            .get(path.as_ref())
+            .map(|source| source.text.as_str())
+    }
+
+    /// Parse the root file into a `Protocol`, splicing in the domains of included files.
+    ///
+    /// A file included more than once, e.g. by two files both including it, is only spliced in the first time.
+    pub fn parse(&self) -> Result<Protocol<'_>, LoadError> {
+        let mut stack = vec![];
+        let mut spliced = HashSet::new();
+        let text = self.files[&self.root].text.as_str();
+        let (description, version, entries) = self.parse_file(&self.root, root_file, &stack)?;
+        let mut domains = vec![];
+
+        spliced.insert(self.root.as_path());
+        self.expand(
+            &self.root,
+            text,
+            entries,
+            &mut stack,
+            &mut spliced,
+            &mut domains,
+        )?;
+
+        Ok(Protocol {
+            description,
+            version,
+            domains,
+        })
+    }
+
+    fn parse_file<'a, O>(
+        &'a self,
+        path: &Path,
+        parser: fn(&'a str) -> IResult<'a, O>,
+        stack: &[IncludedFrom],
+    ) -> Result<O, LoadError> {
+        let text = self.files[path].text.as_str();
+        let located = |mut err: ParseError| {
+            let inner = err.inner_mut();
+
+            inner.path = Some(path.to_owned());
+            inner.included_from = stack.iter().rev().cloned().collect();
+
+            LoadError::Parse(err)
+        };
+
+        match parser(text) {
+            Ok(("", output)) => Ok(output),
+            Ok((rest, _)) => Err(located(ParseError::new(text, unparsed(rest)))),
+            Err(err) => Err(located(ParseError::from_nom(text, err))),
+        }
+    }
+
+    fn expand<'a>(
+        &'a self,
+        path: &Path,
+        text: &'a str,
+        entries: Vec<Entry<'a>>,
+        stack: &mut Vec<IncludedFrom>,
+        spliced: &mut HashSet<&'a Path>,
+        domains: &mut Vec<Domain<'a>>,
+    ) -> Result<(), LoadError> {
+        let index = LineIndex::new(text);
+
+        for entry in entries {
+            match entry {
+                Entry::Domain(mut domain) => {
+                    domain.locate(&index);
+                    domains.push(domain);
+                }
+                Entry::Include(name) => {
+                    let line = line_at(text, text.offset(name));
+                    // `load` scans every line with the same `include` grammar and loads its target
+                    let (target, source) = self
+                        .files
+                        .get(path)
+                        .and_then(|source| source.includes.get(&line))
+                        .and_then(|target| self.files.get_key_value(target))
+                        .expect("include loaded with its file");
+
+                    if !spliced.insert(target) {
+                        continue;
+                    }
+
+                    stack.push(IncludedFrom {
+                        path: path.to_owned(),
+                        line,
+                    });
+
+                    let entries = self.parse_file(target, included_file, stack)?;
+
+                    self.expand(target, &source.text, entries, stack, spliced, domains)?;
+
+                    stack.pop();
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROOT: &str = r#"# Copyright 2017 The Chromium Authors. All rights reserved.
+
+version
+  major 1
+  minor 3
+
+include domains/Accessibility.pdl
+include domains/DOM.pdl
+"#;
+
+    const ACCESSIBILITY: &str = r#"# Copyright 2017 The Chromium Authors. All rights reserved.
+
+experimental domain Accessibility
+  depends on DOM
+
+  # Unique accessibility node identifier.
+  type AXNodeId extends string
+"#;
+
+    const DOM: &str = r#"# This domain exposes DOM read/write operations.
+domain DOM
+
+  # Unique DOM node identifier.
+  type NodeId extends integer
+
+include ../shared/Runtime.pdl
+"#;
+
+    const RUNTIME: &str = r#"domain Runtime
+
+  # Unique script identifier.
+  type ScriptId extends string
+"#;
+
+    #[test]
+    fn parse_includes() {
+        let loader = vec![
+            ("browser_protocol.pdl", ROOT),
+            ("domains/Accessibility.pdl", ACCESSIBILITY),
+            ("domains/DOM.pdl", DOM),
+            ("shared/Runtime.pdl", RUNTIME),
+        ]
+        .into_iter()
+        .collect::<MemoryLoader>();
+
+        let sources = Sources::load(&loader, "./browser_protocol.pdl").unwrap();
+        let protocol = sources.parse().unwrap();
+
+        assert_eq!(sources.root(), Path::new("browser_protocol.pdl"));
+        assert_eq!(sources.get("shared/Runtime.pdl"), Some(RUNTIME));
+        assert_eq!(protocol.version, Version::new(1, 3));
+        assert_eq!(
+            protocol
+                .domains
+                .iter()
+                .map(|domain| domain.name.as_ref())
+                .collect::<Vec<_>>(),
+            vec!["Accessibility", "DOM", "Runtime"]
+        );
+        assert!(protocol.domains[0].description.is_empty());
+        assert_eq!(
+            &protocol.domains[1].description[..],
+            &["This domain exposes DOM read/write operations."]
+        );
+        assert_eq!(protocol.domains[2].types[0].id, "ScriptId");
+        assert_eq!(protocol.domains[1].span.line, 2);
+        assert_eq!(protocol.domains[2].types[0].span.line, 4);
+    }
+
+    #[test]
+    fn include_cycle() {
+        let loader = vec![
+            ("browser_protocol.pdl", ROOT),
+            ("domains/Accessibility.pdl", ACCESSIBILITY),
+            ("domains/DOM.pdl", DOM),
+            ("shared/Runtime.pdl", "include ../domains/DOM.pdl\n"),
+        ]
+        .into_iter()
+        .collect::<MemoryLoader>();
+
+        match Sources::load(&loader, "browser_protocol.pdl").unwrap_err() {
+            LoadError::Cycle {
+                path,
+                included_from,
+            } => {
+                assert_eq!(path, Path::new("domains/DOM.pdl"));
+                assert_eq!(
+                    included_from,
+                    vec![
+                        IncludedFrom {
+                            path: "shared/Runtime.pdl".into(),
+                            line: 1
+                        },
+                        IncludedFrom {
+                            path: "domains/DOM.pdl".into(),
+                            line: 7
+                        },
+                        IncludedFrom {
+                            path: "browser_protocol.pdl".into(),
+                            line: 8
+                        },
+                    ]
+                );
+            }
+            err => panic!("unexpected error: {:?}", err),
+        }
+    }
+
+    #[test]
+    fn include_diamond() {
+        let loader = vec![
+            (
+                "browser_protocol.pdl",
+                "version\n  major 1\n  minor 3\n\ninclude A.pdl\ninclude B.pdl\n",
+            ),
+            ("A.pdl", "domain A\n\ninclude C.pdl\n"),
+            ("B.pdl", "domain B\n\ninclude C.pdl\n"),
+            ("C.pdl", "domain C\n"),
+        ]
+        .into_iter()
+        .collect::<MemoryLoader>();
+
+        let sources = Sources::load(&loader, "browser_protocol.pdl").unwrap();
+        let protocol = sources.parse().unwrap();
+
+        assert_eq!(
+            protocol
+                .domains
+                .iter()
+                .map(|domain| domain.name.as_ref())
+                .collect::<Vec<_>>(),
+            vec!["A", "C", "B"]
+        );
+    }
+
+    #[test]
+    fn include_missing_file() {
+        let loader = vec![("browser_protocol.pdl", ROOT)]
+            .into_iter()
+            .collect::<MemoryLoader>();
+
+        let err = Sources::load(&loader, "browser_protocol.pdl").unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            r#"in file included from browser_protocol.pdl:7
+fail to load domains/Accessibility.pdl, domains/Accessibility.pdl not found"#
+        );
+    }
+
+    #[test]
+    fn include_parse_error() {
+        let loader = vec![
+            ("browser_protocol.pdl", ROOT),
+            ("domains/Accessibility.pdl", ACCESSIBILITY),
+            ("domains/DOM.pdl", DOM),
+            ("shared/Runtime.pdl", "domain Runtime\n  typo ScriptId\n"),
+        ]
+        .into_iter()
+        .collect::<MemoryLoader>();
+
+        let sources = Sources::load(&loader, "browser_protocol.pdl").unwrap();
+
+        match sources.parse().unwrap_err() {
+            LoadError::Parse(err) => {
+                assert_eq!(err.path(), Some(Path::new("shared/Runtime.pdl")));
+                assert_eq!((err.line(), err.column()), (2, 3));
+                assert_eq!(
+                    err.to_string(),
+                    r#"in file included from domains/DOM.pdl:7
+in file included from browser_protocol.pdl:8
+shared/Runtime.pdl:2:3: expected `type`, `command` or `event`
+  |
+2 |   typo ScriptId
+  |   ^"#
+                );
+            }
+            err => panic!("unexpected error: {:?}", err),
+        }
+    }
+
+    #[test]
+    fn parse_file_and_reader() {
+        let dir = std::env::temp_dir().join(format!("pdl-parse-file-{}", std::process::id()));
+
+        fs::create_dir_all(dir.join("domains")).unwrap();
+        fs::create_dir_all(dir.join("shared")).unwrap();
+
+        for (path, content) in &[
+            ("browser_protocol.pdl", ROOT),
+            ("domains/Accessibility.pdl", ACCESSIBILITY),
+            ("domains/DOM.pdl", DOM),
+            ("shared/Runtime.pdl", "domain Runtime\n  typo ScriptId\n"),
+        ] {
+            fs::write(dir.join(path), content).unwrap();
+        }
+
+        let err = parse_file(dir.join("browser_protocol.pdl")).unwrap_err();
+
+        assert!(err.to_string().contains(&format!(
+            "{}:2:3: expected",
+            dir.join("shared/Runtime.pdl").display()
+        )));
+
+        fs::write(dir.join("shared/Runtime.pdl"), RUNTIME).unwrap();
+
+        let protocol = parse_file(dir.join("browser_protocol.pdl")).unwrap();
+
+        assert_eq!(protocol.domains.len(), 3);
+        assert_eq!(protocol.domains[2].types[0].id, "ScriptId");
+
+        let err = parse_reader(ROOT.as_bytes()).unwrap_err();
+
+        assert!(err.to_string().starts_with(
+            "in file included from <reader>:7\nfail to load domains/Accessibility.pdl, "
+        ));
+
+        let protocol = parse_reader(fs::File::open(dir.join("domains/DOM.pdl")).unwrap());
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(protocol.is_err());
+        assert_eq!(
+            parse_reader(io::Cursor::new(ROOT.replace(
+                "include domains/Accessibility.pdl\ninclude domains/DOM.pdl\n",
+                RUNTIME
+            )))
+            .unwrap()
+            .domains[0]
+                .name,
+            "Runtime"
+        );
+    }
+}
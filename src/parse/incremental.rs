@@ -0,0 +1,397 @@
+use std::ops::Range;
+
+use super::locate::Locator;
+use super::*;
+
+/// A change to the text of a `Document`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TextEdit {
+    /// The byte range of the replaced text, in the text before the edit.
+    pub range: Range<usize>,
+    /// The replacement text.
+    pub text: String,
+}
+
+impl TextEdit {
+    pub fn new<S: Into<String>>(range: Range<usize>, text: S) -> Self {
+        TextEdit {
+            range,
+            text: text.into(),
+        }
+    }
+}
+
+/// A PDL source with its parsed `Protocol`, kept up to date as the source is edited.
+///
+/// Each edit only reparses the domains it touches, reusing the rest of the `Protocol`;
+/// edits which change the boundaries between domains or the protocol header fall back
+/// to a full parse. The result is always the same as parsing the new text with `parse_str`.
+///
+/// ```
+/// use pdl::{Document, TextEdit};
+///
+/// let text = "version\n  major 1\n  minor 3\n\ndomain Log\n  command enable\n";
+/// let mut doc = Document::parse(text).unwrap();
+/// let start = text.find("enable").unwrap();
+///
+/// doc.edit(&TextEdit::new(start..start + "enable".len(), "disable"))
+///     .unwrap();
+///
+/// assert_eq!(doc.protocol().domains[0].commands[0].name, "disable");
+/// ```
+#[derive(Clone, Debug)]
+pub struct Document {
+    text: String,
+    protocol: Protocol<'static>,
+    /// The byte offset following the version of the protocol.
+    header_end: usize,
+    /// Whether the `protocol` is out of date after an edit failed to parse.
+    stale: bool,
+}
+
+impl Document {
+    /// Parse the whole text of a document.
+    pub fn parse<S: Into<String>>(text: S) -> Result<Self, ParseError> {
+        let mut doc = Document {
+            text: text.into(),
+            protocol: Protocol {
+                description: Default::default(),
+                version: Default::default(),
+                domains: vec![],
+            },
+            header_end: 0,
+            stale: true,
+        };
+
+        doc.parse_all()?;
+
+        Ok(doc)
+    }
+
+    /// The current text of the document.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The `Protocol` parsed from the text.
+    ///
+    /// If the last edit failed to parse, this is the `Protocol` before it.
+    pub fn protocol(&self) -> &Protocol<'static> {
+        &self.protocol
+    }
+
+    /// Take the `Protocol` parsed from the text.
+    pub fn into_protocol(self) -> Protocol<'static> {
+        self.protocol
+    }
+
+    /// Apply an edit to the text, and reparse the affected domains.
+    ///
+    /// The text is edited even if it fails to parse, in which case the next edit reparses it whole.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range of the edit is out of the text or not on a character boundary.
+    pub fn edit(&mut self, edit: &TextEdit) -> Result<(), ParseError> {
+        let Range { start, end } = edit.range.clone();
+        let removed_lines = self.text[start..end].matches('\n').count();
+
+        self.text.replace_range(start..end, &edit.text);
+
+        if self.stale || start < self.header_end || !self.reparse(edit, removed_lines) {
+            self.parse_all()
+        } else {
+            Ok(())
+        }
+    }
+
+    fn parse_all(&mut self) -> Result<(), ParseError> {
+        self.stale = true;
+
+        let protocol = parse_str(&self.text)?;
+        let (rest, _) = tuple((bom, description, empty_lines, version))(self.text.as_str())
+            .map_err(|err| ParseError::from_nom(&self.text, err))?;
+
+        self.header_end = self.text.len() - rest.len();
+        self.protocol = protocol.into_owned();
+        self.stale = false;
+
+        Ok(())
+    }
+
+    /// Reparse the domains touched by an edit already applied to the text.
+    ///
+    /// Returns `false` if the domains can't be reparsed on their own.
+    fn reparse(&mut self, edit: &TextEdit, removed_lines: usize) -> bool {
+        let domains = &self.protocol.domains;
+
+        if domains.is_empty() {
+            return false;
+        }
+
+        // domain `i` and the blank lines and comments before it span `bounds[i]..bounds[i + 1]`
+        let bounds = Some(self.header_end)
+            .into_iter()
+            .chain(domains.iter().map(|domain| domain.span.end))
+            .collect::<Vec<_>>();
+        let Range { start, end } = edit.range;
+        let first = (0..domains.len())
+            .find(|&i| start <= bounds[i + 1])
+            .unwrap_or(domains.len() - 1);
+        let last = (0..domains.len())
+            .rev()
+            .find(|&i| end >= bounds[i])
+            .unwrap_or(0)
+            .max(first);
+        let at_tail = end >= bounds[domains.len()];
+        let delta = edit.text.len() as isize - (end - start) as isize;
+        let reparse_end = if at_tail {
+            self.text.len()
+        } else {
+            (bounds[last + 1] as isize + delta) as usize
+        };
+
+        trace!(
+            "reparse domains {}..={} in {}..{}",
+            first,
+            last,
+            bounds[first],
+            reparse_end
+        );
+
+        let mut rest = &self.text[bounds[first]..];
+        let mut reparsed = vec![];
+
+        loop {
+            let offset = self.text.len() - rest.len();
+
+            if offset == reparse_end && !at_tail {
+                break;
+            }
+            if offset > reparse_end {
+                return false;
+            }
+            if at_tail && matches!(empty_lines(rest), Ok(("", _))) {
+                break;
+            }
+
+            match preceded(empty_lines, domain)(rest) {
+                Ok((remaining, domain)) => {
+                    reparsed.push(domain);
+                    rest = remaining;
+                }
+                Err(_) => return false,
+            }
+        }
+
+        if reparsed.is_empty() && domains.len() == last - first + 1 {
+            return false;
+        }
+
+        reparsed.locate(&LineIndex::new(&self.text));
+
+        let reparsed = reparsed
+            .into_iter()
+            .map(Domain::into_owned)
+            .collect::<Vec<_>>();
+
+        let shift = Shift {
+            bytes: delta,
+            lines: edit.text.matches('\n').count() as isize - removed_lines as isize,
+        };
+        let domains = &mut self.protocol.domains;
+
+        domains[last + 1..].locate(&shift);
+        domains.splice(first..=last, reparsed);
+
+        true
+    }
+}
+
+/// Move located spans following an edit.
+struct Shift {
+    bytes: isize,
+    lines: isize,
+}
+
+impl Locator for Shift {
+    fn locate(&self, span: Span) -> Span {
+        Span {
+            start: (span.start as isize + self.bytes) as usize,
+            end: (span.end as isize + self.bytes) as usize,
+            line: (span.line as isize + self.lines) as usize,
+            column: span.column,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r#"# Copyright 2017 The Chromium Authors. All rights reserved.
+
+version
+  major 1
+  minor 3
+
+# The Console domain.
+domain Console
+  # Does nothing.
+  command clearMessages
+
+  event messageAdded
+    parameters
+      string text
+
+experimental domain Log
+  depends on Runtime
+
+  type LogEntry extends object
+    properties
+      enum source
+        xml
+        javascript
+      string text
+
+  command enable
+
+domain Runtime
+  command enable
+
+"#;
+
+    /// Apply an edit replacing `old` with `new`, and check the result against a full parse.
+    fn edit(doc: &mut Document, old: &str, new: &str) -> Result<(), ParseError> {
+        let start = doc.text().find(old).unwrap();
+        let res = doc.edit(&TextEdit::new(start..start + old.len(), new));
+
+        match parse_str(doc.text()) {
+            Ok(protocol) => {
+                assert!(res.is_ok());
+                // compare the spans too
+                assert_eq!(
+                    format!("{:?}", doc.protocol()),
+                    format!("{:?}", protocol),
+                    "{}",
+                    doc.text()
+                );
+            }
+            Err(err) => assert_eq!(res.as_ref().unwrap_err().to_string(), err.to_string()),
+        }
+
+        res
+    }
+
+    fn names(doc: &Document) -> Vec<*const u8> {
+        doc.protocol()
+            .domains
+            .iter()
+            .map(|domain| domain.name.as_ptr())
+            .collect()
+    }
+
+    #[test]
+    fn reparse_edited_domain() {
+        let mut doc = Document::parse(SOURCE).unwrap();
+        let before = names(&doc);
+
+        edit(&mut doc, "xml\n", "xml\n        network\n").unwrap();
+        edit(
+            &mut doc,
+            "depends on Runtime\n",
+            "depends on Runtime\n  depends on Console\n",
+        )
+        .unwrap();
+        edit(
+            &mut doc,
+            "  command clearMessages",
+            "  command clearMessages\n    parameters\n      boolean force",
+        )
+        .unwrap();
+
+        let after = names(&doc);
+
+        // the untouched domain is reused
+        assert_ne!(before[0], after[0]);
+        assert_ne!(before[1], after[1]);
+        assert_eq!(before[2], after[2]);
+        assert_eq!(
+            doc.protocol().domains[0].commands[0].parameters[0].name,
+            "force"
+        );
+    }
+
+    #[test]
+    fn reparse_domain_boundaries() {
+        let mut doc = Document::parse(SOURCE).unwrap();
+
+        // append a domain
+        edit(
+            &mut doc,
+            "  command enable\n\n",
+            "  command enable\n\ndomain Network\n  command enable\n",
+        )
+        .unwrap();
+        assert_eq!(doc.protocol().domains.len(), 4);
+
+        // a comment at the end of a domain describes the following one
+        edit(
+            &mut doc,
+            "      string text\n\n",
+            "      string text\n# The Log domain.\n",
+        )
+        .unwrap();
+        assert_eq!(
+            &doc.protocol().domains[1].description[..],
+            &["The Log domain."]
+        );
+
+        // remove a domain
+        edit(&mut doc, "domain Runtime\n  command enable\n", "").unwrap();
+        assert_eq!(doc.protocol().domains.len(), 3);
+
+        // merge two domains
+        edit(
+            &mut doc,
+            "\n# The Log domain.\nexperimental domain Log\n",
+            "\n",
+        )
+        .unwrap_err();
+        edit(&mut doc, "depends on Runtime\n", "").unwrap();
+        assert_eq!(doc.protocol().domains.len(), 2);
+        assert_eq!(doc.protocol().domains[0].types[0].id, "LogEntry");
+    }
+
+    #[test]
+    fn reparse_header() {
+        let mut doc = Document::parse(SOURCE).unwrap();
+
+        edit(&mut doc, "minor 3", "minor 4").unwrap();
+        assert_eq!(doc.protocol().version, Version::new(1, 4));
+
+        edit(&mut doc, "major 1", "major").unwrap_err();
+        edit(&mut doc, "  command clearMessages", "  command clear").unwrap_err();
+        edit(&mut doc, "major", "major 2").unwrap();
+        assert_eq!(doc.protocol().domains[0].commands[0].name, "clear");
+    }
+
+    #[test]
+    fn reparse_errors() {
+        let mut doc = Document::parse(SOURCE).unwrap();
+
+        edit(&mut doc, "  event messageAdded", "  evnt messageAdded").unwrap_err();
+        assert_eq!(doc.protocol().domains[0].events[0].name, "messageAdded");
+
+        edit(&mut doc, "  evnt messageAdded", "  event messageRemoved").unwrap();
+        assert_eq!(doc.protocol().domains[0].events[0].name, "messageRemoved");
+
+        edit(
+            &mut doc,
+            "domain Runtime\n  command enable\n",
+            "domain Runtime\n  comand enable\n",
+        )
+        .unwrap_err();
+        edit(&mut doc, "comand", "command").unwrap();
+    }
+}
@@ -0,0 +1,207 @@
+use super::recover::{is_top_level, next_line};
+use super::*;
+
+/// An iterator parsing the domains of a PDL source one at a time.
+///
+/// The description and version of the protocol are parsed up front, then each
+/// domain is only parsed when it is reached, so callers can stop early or skip
+/// the domains they aren't interested in.
+///
+/// ```
+/// let source = "version\n  major 1\n  minor 3\n\ndomain Console\n\ndomain Log\n  command enable\n";
+/// let mut domains = pdl::DomainIter::new(source).unwrap();
+///
+/// let log = domains.find_domain("Log").unwrap().unwrap();
+///
+/// assert_eq!(log.commands[0].name, "enable");
+/// assert!(domains.next().is_none());
+/// ```
+pub struct DomainIter<'a> {
+    input: &'a str,
+    rest: &'a str,
+    index: LineIndex<'a>,
+    description: Description<'a>,
+    version: Version<'a>,
+}
+
+impl<'a> DomainIter<'a> {
+    /// Parse the description and version of the protocol, leaving the domains to the iterator.
+    pub fn new(input: &'a str) -> Result<Self, ParseError> {
+        let (rest, (_bom, description, _, version)) =
+            context("protocol", tuple((bom, description, empty_lines, version)))(input)
+                .map_err(|err| ParseError::from_nom(input, err))?;
+
+        Ok(DomainIter {
+            input,
+            rest,
+            index: LineIndex::new(input),
+            description,
+            version,
+        })
+    }
+
+    /// The description of the protocol.
+    pub fn description(&self) -> &Description<'a> {
+        &self.description
+    }
+
+    /// The version of the protocol.
+    pub fn version(&self) -> &Version<'a> {
+        &self.version
+    }
+
+    /// The name of the next domain, parsing only its header.
+    pub fn peek_name(&self) -> Option<Cow<'a, str>> {
+        let rest = empty_lines(self.rest).map_or(self.rest, |(rest, _)| rest);
+        let (_, domain) = domain_header(rest).ok()?;
+
+        Some(domain.name)
+    }
+
+    /// Skip the next domain without parsing its items, returning its name.
+    pub fn skip_domain(&mut self) -> Option<Result<Cow<'a, str>, ParseError>> {
+        let rest = empty_lines(self.rest).map_or(self.rest, |(rest, _)| rest);
+        // skip the description and header, the header being a top level line itself
+        let (mut rest, domain) = match domain_header(rest) {
+            Ok(parsed) => parsed,
+            // the end of the input, or an error
+            Err(_) => return self.next().and_then(Result::err).map(Err),
+        };
+
+        while !rest.is_empty() && !is_top_level(rest) {
+            rest = next_line(rest);
+        }
+
+        self.rest = rest;
+
+        Some(Ok(domain.name))
+    }
+
+    /// Parse the domain named `name`, skipping the domains before it.
+    pub fn find_domain(&mut self, name: &str) -> Option<Result<Domain<'a>, ParseError>> {
+        loop {
+            if self.peek_name().as_deref() == Some(name) {
+                return self.next();
+            }
+            if let Err(err) = self.skip_domain()? {
+                return Some(Err(err));
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for DomainIter<'a> {
+    type Item = Result<Domain<'a>, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = empty_lines(self.rest).map_or(self.rest, |(rest, _)| rest);
+
+        if rest.is_empty() {
+            self.rest = rest;
+
+            return None;
+        }
+
+        match domain(rest) {
+            Ok((rest, mut domain)) => {
+                domain.locate(&self.index);
+                self.rest = rest;
+
+                Some(Ok(domain))
+            }
+            Err(_) => {
+                let err = ParseError::new(self.input, unparsed(rest));
+
+                // stop after the first error
+                self.rest = &rest[rest.len()..];
+
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use super::super::tests::SNIPPET;
+
+    const SOURCE: &str = r#"version
+  major 1
+  minor 3
+
+# The Console domain.
+domain Console
+  command enable
+
+  # Issued when new console message is added.
+  event messageAdded
+    parameters
+      string text
+
+domain Log
+  type LogEntry extends object
+    properties
+      string text
+
+  command enable
+
+domain Runtime
+  command enable
+  comand disable
+"#;
+
+    #[test]
+    fn iter_domains() {
+        let domains = DomainIter::new(SNIPPET).unwrap();
+
+        assert_eq!(domains.version(), &Version::new(1, 3));
+        assert_eq!(
+            domains.collect::<Result<Vec<_>, _>>().unwrap(),
+            parse_str(SNIPPET).unwrap().domains
+        );
+    }
+
+    #[test]
+    fn skip_domains() {
+        let mut domains = DomainIter::new(SOURCE).unwrap();
+
+        assert_eq!(domains.peek_name().as_deref(), Some("Console"));
+        assert_eq!(domains.skip_domain().unwrap().unwrap(), "Console");
+
+        let log = domains.find_domain("Log").unwrap().unwrap();
+
+        assert_eq!(log.types[0].id, "LogEntry");
+        assert_eq!(log.span.line, 14);
+
+        let runtime = domains.next().unwrap().unwrap();
+
+        assert_eq!(runtime.commands.len(), 1);
+        assert_eq!(
+            domains.next().unwrap().unwrap_err().to_string(),
+            r#"23:3: expected `type`, `command` or `event`
+   |
+23 |   comand disable
+   |   ^"#
+        );
+        assert!(domains.next().is_none());
+    }
+
+    #[test]
+    fn skip_described_domain() {
+        let mut domains = DomainIter::new(SOURCE).unwrap();
+
+        assert_eq!(domains.skip_domain().unwrap().unwrap(), "Console");
+        assert_eq!(domains.next().unwrap().unwrap().name, "Log");
+        assert_eq!(domains.skip_domain().unwrap().unwrap(), "Runtime");
+        assert!(domains.next().is_none());
+    }
+
+    #[test]
+    fn find_missing_domain() {
+        let mut domains = DomainIter::new(SOURCE).unwrap();
+
+        assert!(domains.find_domain("Network").is_none());
+    }
+}
@@ -0,0 +1,138 @@
+use crate::*;
+
+/// Maps byte offsets in a source to line and column numbers.
+pub(crate) struct LineIndex<'a> {
+    source: &'a str,
+    /// The byte offset of the start of each line.
+    lines: Vec<usize>,
+}
+
+/// Maps the spans recorded while parsing to their final location.
+pub(crate) trait Locator {
+    fn locate(&self, span: Span) -> Span;
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(source: &'a str) -> Self {
+        let lines = Some(0)
+            .into_iter()
+            .chain(source.match_indices('\n').map(|(pos, _)| pos + 1))
+            .collect();
+
+        LineIndex { source, lines }
+    }
+}
+
+impl Locator for LineIndex<'_> {
+    /// Turn a span counted from the end of the source into a located one.
+    fn locate(&self, span: Span) -> Span {
+        let start = self.source.len() - span.start;
+        let end = self.source.len() - span.end;
+        let line = self.lines.partition_point(|&pos| pos <= start);
+        let column = self.source[self.lines[line - 1]..start].chars().count() + 1;
+
+        Span {
+            start,
+            end,
+            line,
+            column,
+        }
+    }
+}
+
+/// Locate the spans of a node and its children once the whole source has been parsed.
+pub(crate) trait Locate {
+    fn locate(&mut self, index: &dyn Locator);
+}
+
+impl<T: Locate> Locate for [T] {
+    fn locate(&mut self, index: &dyn Locator) {
+        for node in self {
+            node.locate(index);
+        }
+    }
+}
+
+impl Locate for Protocol<'_> {
+    fn locate(&mut self, index: &dyn Locator) {
+        self.domains.locate(index);
+    }
+}
+
+impl Locate for Domain<'_> {
+    fn locate(&mut self, index: &dyn Locator) {
+        self.span = index.locate(self.span);
+        self.dependencies.locate(index);
+        self.types.locate(index);
+        self.commands.locate(index);
+        self.events.locate(index);
+    }
+}
+
+impl Locate for Dependency<'_> {
+    fn locate(&mut self, index: &dyn Locator) {
+        self.span = index.locate(self.span);
+    }
+}
+
+impl Locate for TypeDef<'_> {
+    fn locate(&mut self, index: &dyn Locator) {
+        self.span = index.locate(self.span);
+        self.extends.locate(index);
+
+        match self.item {
+            Some(Item::Enum(ref mut variants)) => variants.locate(index),
+            Some(Item::Properties(ref mut props)) => props.locate(index),
+            None => {}
+        }
+    }
+}
+
+impl Locate for Type<'_> {
+    fn locate(&mut self, index: &dyn Locator) {
+        match self {
+            Type::Enum(variants) => variants.locate(index),
+            Type::ArrayOf(ty) => ty.locate(index),
+            _ => {}
+        }
+    }
+}
+
+impl Locate for Variant<'_> {
+    fn locate(&mut self, index: &dyn Locator) {
+        self.span = index.locate(self.span);
+    }
+}
+
+impl Locate for Param<'_> {
+    fn locate(&mut self, index: &dyn Locator) {
+        self.span = index.locate(self.span);
+        self.ty.locate(index);
+    }
+}
+
+impl Locate for Command<'_> {
+    fn locate(&mut self, index: &dyn Locator) {
+        self.span = index.locate(self.span);
+
+        if let Some(ref mut redirect) = self.redirect {
+            redirect.locate(index);
+        }
+
+        self.parameters.locate(index);
+        self.returns.locate(index);
+    }
+}
+
+impl Locate for Event<'_> {
+    fn locate(&mut self, index: &dyn Locator) {
+        self.span = index.locate(self.span);
+        self.parameters.locate(index);
+    }
+}
+
+impl Locate for Redirect<'_> {
+    fn locate(&mut self, index: &dyn Locator) {
+        self.span = index.locate(self.span);
+    }
+}
@@ -0,0 +1,469 @@
+use std::cell::RefCell;
+
+use super::*;
+
+/// Parse a `Protocol` from a string of PDL format, recovering from errors.
+///
+/// A malformed parameter, item or domain is reported as a `Diagnostic`, and the parser skips
+/// to the next parameter, item or domain header instead of stopping, so the returned `Protocol`
+/// contains everything that could be parsed.
+pub fn parse_recovering(input: &str) -> (Protocol<'_>, Vec<Diagnostic>) {
+    let mut diagnostics = vec![];
+
+    let (mut rest, (description, version)) = match header(input) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            let at = report(input, err, &mut diagnostics);
+            let rest = if domain_header(at).is_ok() {
+                at
+            } else {
+                resync(input, at, is_top_level)
+            };
+
+            (rest, Default::default())
+        }
+    };
+
+    let mut domains = vec![];
+
+    loop {
+        rest = skip_empty_lines(rest);
+
+        if rest.is_empty() {
+            break;
+        }
+
+        match domain_header(rest) {
+            Ok((remaining, mut domain)) => {
+                rest = domain_items(input, remaining, &mut domain, &mut diagnostics);
+                domains.push(domain);
+            }
+            Err(err) => {
+                let at = report(input, err, &mut diagnostics);
+
+                rest = resync(rest, at, is_top_level);
+            }
+        }
+    }
+
+    let mut protocol = Protocol {
+        description,
+        version,
+        domains,
+    };
+
+    protocol.locate(&LineIndex::new(input));
+
+    (protocol, diagnostics)
+}
+
+fn header(input: &str) -> IResult<'_, (Description<'_>, Version<'_>)> {
+    map(
+        tuple((bom, description, empty_lines, version)),
+        |(_bom, description, _, version)| (description, version),
+    )(input)
+}
+
+/// Parse the items of a `domain`, returning the input following the domain.
+fn domain_items<'a>(
+    source: &'a str,
+    mut input: &'a str,
+    domain: &mut Domain<'a>,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> &'a str {
+    let recovered = RefCell::new(vec![]);
+    let params = params_recovering(source, &recovered);
+    let domain_item = domain_item_with(&params);
+
+    loop {
+        domain.span.end = input.len();
+
+        let rest = skip_empty_lines(input);
+
+        if rest.is_empty() {
+            return input;
+        }
+
+        // the guard must not outlive the parse, as `params` borrows the diagnostics again
+        recovered.borrow_mut().clear();
+
+        match domain_item(rest) {
+            Ok((remaining, item)) => {
+                diagnostics.append(&mut recovered.borrow_mut());
+                domain.push(item);
+                input = remaining;
+            }
+            Err(_) if is_top_level(rest) => return input,
+            Err(err) => {
+                let at = report(source, err, diagnostics);
+
+                input = resync(rest, at, |s| domain_item(s).is_ok() || is_top_level(s));
+            }
+        }
+    }
+}
+
+/// Parse the parameters or properties following a `parameters`, `returns` or `properties` line,
+/// reporting a malformed one to `diagnostics` and skipping to the next one.
+///
+/// The list goes on as long as the strict grammar accepts a parameter; a line it rejects is only
+/// reported as malformed if it is indented deeper than the line introducing the list, and the
+/// strict grammar can't go on there either, e.g. with an over-indented item.
+fn params_recovering<'a, 'd>(
+    source: &'a str,
+    diagnostics: &'d RefCell<Vec<Diagnostic>>,
+) -> impl Fn(&'a str) -> IResult<'a, Vec<Param<'a>>> + 'd
+where
+    'a: 'd,
+{
+    move |input: &'a str| {
+        let header = source[..source.len() - input.len()]
+            .trim_end_matches('\n')
+            .rfind('\n')
+            .map_or(source, |pos| &source[pos + 1..]);
+        let level = indentation(header);
+        let mut params = vec![];
+        let mut reported = false;
+        let mut rest = input;
+
+        while !rest.is_empty() {
+            match param(rest) {
+                Ok((remaining, param)) => {
+                    params.push(param);
+                    rest = remaining;
+                }
+                Err(_) if indentation(skip_comments(rest)) <= level || after_params(rest) => break,
+                Err(err) => {
+                    let param_level = indentation(skip_comments(rest));
+                    let at = report_line(source, err, &mut diagnostics.borrow_mut());
+
+                    reported = true;
+                    rest = next_line(at);
+
+                    // skip what's left of the malformed parameter, like its enum variants
+                    while !rest.is_empty() && indentation(rest) > param_level {
+                        rest = next_line(rest);
+                    }
+                }
+            }
+        }
+
+        if params.is_empty() && !reported {
+            // nothing looks like a parameter, fail like the strict grammar does
+            params = many1(param)(rest)?.1;
+        }
+
+        Ok((rest, params))
+    }
+}
+
+/// The indentation of a line, or zero if it is blank.
+fn indentation(line: &str) -> usize {
+    let content = line.trim_start_matches([' ', '\t']);
+
+    if content.starts_with(['\n', '\r']) || content.is_empty() {
+        0
+    } else {
+        line.len() - content.len()
+    }
+}
+
+/// The input following the line `input` starts in.
+pub(super) fn next_line(input: &str) -> &str {
+    input
+        .find('\n')
+        .map_or(&input[input.len()..], |pos| &input[pos + 1..])
+}
+
+/// Whether the line starts a top level construct, like a domain or its description.
+pub(super) fn is_top_level(input: &str) -> bool {
+    input.starts_with(|c: char| !c.is_whitespace() && c != '#') || domain_header(input).is_ok()
+}
+
+fn skip_comments(input: &str) -> &str {
+    description(input).map_or(input, |(rest, _)| rest)
+}
+
+fn skip_empty_lines(input: &str) -> &str {
+    empty_lines(input).map_or(input, |(rest, _)| rest)
+}
+
+/// Skip to the first line where `synced` succeeds, from the error position `at`.
+///
+/// The line of the error is only retried if the error is at its start and
+/// beyond the `start` of the failed construct, so the parser always makes progress.
+fn resync<'a, F>(start: &'a str, at: &'a str, synced: F) -> &'a str
+where
+    F: Fn(&'a str) -> bool,
+{
+    let at_line_start =
+        at.len() < start.len() && start.as_bytes()[start.len() - at.len() - 1] == b'\n';
+    let mut input = if at_line_start { at } else { next_line(at) };
+
+    while !input.is_empty() && !synced(input) {
+        input = next_line(input);
+    }
+
+    input
+}
+
+/// Report an error as a `Diagnostic`, returning where it occurred.
+fn report<'a>(
+    source: &'a str,
+    err: nom::Err<Error<'a>>,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> &'a str {
+    let at = match err {
+        nom::Err::Error(ref err) | nom::Err::Failure(ref err) => err.input,
+        nom::Err::Incomplete(_) => &source[source.len()..],
+    };
+    let err = ParseError::from_nom(source, err);
+
+    debug!("recover from error, {}", err);
+
+    diagnostics.push(err.into());
+
+    at
+}
+
+/// Report an error as a `Diagnostic` spanning the rest of its line from the first non-blank character,
+/// returning where it occurred.
+fn report_line<'a>(
+    source: &'a str,
+    err: nom::Err<Error<'a>>,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> &'a str {
+    let at = report(source, err, diagnostics);
+
+    if let Some(diag) = diagnostics.last_mut() {
+        let line_start = source[..diag.span.start]
+            .rfind('\n')
+            .map_or(0, |pos| pos + 1);
+        let content = line_start + indentation(&source[line_start..]);
+
+        if content < diag.span.start {
+            diag.span.column -= source[content..diag.span.start].chars().count();
+            diag.span.start = content;
+        }
+    }
+
+    at
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recover_nothing() {
+        let input = r#"version
+  major 1
+  minor 3
+
+domain Console
+  # Does nothing.
+  command clearMessages
+"#;
+        let (protocol, diagnostics) = parse_recovering(input);
+
+        assert_eq!(diagnostics, vec![]);
+        assert_eq!(protocol, parse(input).unwrap().1);
+    }
+
+    #[test]
+    fn recover_nothing_from_valid_sources() {
+        let sources = [
+            super::super::tests::SNIPPET,
+            super::super::tests::MODIFIERS,
+            super::super::tests::DESCRIPTIONS,
+            include_str!("../../tests/data/protocol.pdl"),
+            r#"version
+  major 1
+  minor 3
+
+domain Console
+  command x
+    parameters
+      string a
+# Column 0 comment.
+      string b
+
+  event messageAdded
+"#,
+            r#"version
+  major 1
+  minor 3
+
+domain Console
+  event e
+    parameters
+      string a
+      type T extends string
+"#,
+        ];
+
+        for source in &sources {
+            let (protocol, diagnostics) = parse_recovering(source);
+
+            assert_eq!(diagnostics, vec![]);
+            assert_eq!(protocol, parse_str(source).unwrap());
+        }
+    }
+
+    #[test]
+    fn recover_from_malformed_item_before_params() {
+        let (protocol, diagnostics) = parse_recovering(
+            "version\n  major 1\n  minor 3\n\ndomain A\n  comand x\n  command y\n    parameters\n      string\n",
+        );
+
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|diag| diag.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "6:3: error[syntax]: expected `type`, `command` or `event`",
+                "9:7: error[syntax]: expected a name in param",
+            ]
+        );
+        assert_eq!(protocol.domains[0].commands[0].name, "y");
+    }
+
+    #[test]
+    fn recover_from_malformed_items() {
+        let (protocol, diagnostics) = parse_recovering(
+            r#"version
+  major 1
+  minor 3
+
+domain Console
+  type ConsoleMessage extends object
+    properties
+      string source
+      string text extra
+      integer line
+
+  # Does nothing.
+  command clearMessages
+
+  comand disable
+
+  event messageAdded
+
+domain Log
+  command enable
+"#,
+        );
+
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|diag| (diag.span.line, diag.span.column, diag.message.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (9, 7, "expected end of line in param"),
+                (15, 3, "expected `type`, `command` or `event`"),
+            ]
+        );
+        assert!(diagnostics.iter().all(Diagnostic::is_error));
+
+        let console = &protocol.domains[0];
+
+        match console.types[0].item {
+            Some(Item::Properties(ref props)) => assert_eq!(
+                props
+                    .iter()
+                    .map(|prop| prop.name.as_ref())
+                    .collect::<Vec<_>>(),
+                vec!["source", "line"]
+            ),
+            _ => panic!("unexpected item: {:?}", console.types[0].item),
+        }
+        assert_eq!(console.commands[0].name, "clearMessages");
+        assert_eq!(console.events[0].name, "messageAdded");
+        assert_eq!(console.span.line, 5);
+        assert_eq!(console.span.end, protocol.domains[1].span.start - 1);
+        assert_eq!(protocol.domains[1].commands[0].name, "enable");
+    }
+
+    #[test]
+    fn recover_from_malformed_params() {
+        let (protocol, diagnostics) = parse_recovering(
+            r#"version
+  major 1
+  minor 3
+
+domain Console
+  command x
+    parameters
+      string
+      string b
+      # Message level.
+      enum level
+        log
+        warning
+    returns
+      enum
+        log
+      string c
+
+  event messageAdded
+"#,
+        );
+
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|diag| diag.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "8:7: error[syntax]: expected a name in param",
+                "15:7: error[syntax]: expected a name in param",
+            ]
+        );
+
+        let command = &protocol.domains[0].commands[0];
+        let names = |params: &[Param]| {
+            params
+                .iter()
+                .map(|param| param.name.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(names(&command.parameters), vec!["b", "level"]);
+        assert_eq!(names(&command.returns), vec!["c"]);
+        assert_eq!(command.parameters[1].span.line, 11);
+        assert_eq!(protocol.domains[0].events[0].name, "messageAdded");
+    }
+
+    #[test]
+    fn recover_from_malformed_domains() {
+        let (protocol, diagnostics) = parse_recovering(
+            r#"version
+  major 1
+
+experimental domain
+  command enable
+
+# The Log domain.
+domain Log
+  command enable
+"#,
+        );
+
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|diag| diag.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "3:1: error[syntax]: expected indentation in version",
+                "4:20: error[syntax]: expected ' ' in domain",
+            ]
+        );
+        assert_eq!(protocol.version, Version::default());
+        assert_eq!(protocol.domains.len(), 1);
+        assert_eq!(&protocol.domains[0].description[..], &["The Log domain."]);
+        assert_eq!(protocol.domains[0].commands[0].name, "enable");
+    }
+}
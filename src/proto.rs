@@ -0,0 +1,770 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt::Write;
+
+use crate::resolve::split;
+use crate::*;
+
+/// A `.proto` file generated for a `Domain`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProtoFile {
+    /// The path of the file, following its package, e.g. `cdp/page.proto`.
+    pub path: String,
+    /// The package of the file, e.g. `cdp.page`.
+    pub package: String,
+    /// The content of the file.
+    pub source: String,
+}
+
+impl Protocol<'_> {
+    /// Turn every `Domain` into a `.proto` file (proto3) of its own package under `package`.
+    ///
+    /// An object type, the parameters and returns of a command and the parameters of an event
+    /// become messages, e.g. `Frame`, `NavigateParams`, `NavigateReturns` and `FrameNavigatedEvent`.
+    /// Enums become enums, nested in the message for inline ones, and an array type becomes a message
+    /// with its elements in `items`, like the message nested for the inner array of an array of arrays.
+    /// The other types are inlined where they are used.
+    ///
+    /// Field and enum value numbers are hashes of their names rather than their positions,
+    /// so they stay the same when the protocol grows, unless a new name collides with an existing one.
+    /// An inline enum is named after its field, with an `Enum` suffix if a message or enum of the package
+    /// already has that name, so it doesn't shadow it.
+    pub fn to_proto(&self, package: &str) -> Vec<ProtoFile> {
+        let symbols = self.symbols();
+
+        self.domains
+            .iter()
+            .map(|domain| {
+                Exporter {
+                    package,
+                    domain,
+                    symbols: &symbols,
+                    imports: BTreeSet::new(),
+                    names: top_level_names(domain),
+                }
+                .file(&self.version)
+            })
+            .collect()
+    }
+}
+
+/// The names of the messages and enums a `Domain` defines in its package.
+fn top_level_names(domain: &Domain) -> HashSet<String> {
+    let types = domain.types.iter().map(|ty| ty.id.to_string());
+    let commands = domain.commands.iter().flat_map(|cmd| {
+        let name = pascal_case(&cmd.name);
+
+        vec![format!("{}Params", name), format!("{}Returns", name)]
+    });
+    let events = domain
+        .events
+        .iter()
+        .map(|evt| format!("{}Event", pascal_case(&evt.name)));
+
+    types.chain(commands).chain(events).collect()
+}
+
+/// The package of a `Domain` under `package`.
+fn domain_package(package: &str, domain: &str) -> String {
+    format!("{}.{}", package, domain.to_lowercase())
+}
+
+const STRUCT_PROTO: &str = "google/protobuf/struct.proto";
+const VALUE: &str = "google.protobuf.Value";
+
+struct Exporter<'a> {
+    package: &'a str,
+    domain: &'a Domain<'a>,
+    symbols: &'a SymbolTable<'a, 'a>,
+    imports: BTreeSet<String>,
+    /// The names of the messages and enums of the package, which nested enums mustn't shadow.
+    names: HashSet<String>,
+}
+
+/// How a PDL type is spelled in a field.
+enum Kind<'a> {
+    /// A scalar, message or enum type.
+    Named(String),
+    /// An enum defined with the field.
+    Enum(&'a [Variant<'a>]),
+    Repeated(Box<Kind<'a>>),
+}
+
+impl<'a> Exporter<'a> {
+    fn file(mut self, version: &Version) -> ProtoFile {
+        let domain = self.domain;
+        let package = domain_package(self.package, &domain.name);
+        let mut body = String::new();
+
+        for ty in &domain.types {
+            self.type_def(&mut body, ty);
+        }
+        for cmd in &domain.commands {
+            let name = pascal_case(&cmd.name);
+
+            self.message(
+                &mut body,
+                &format!("{}Params", name),
+                &cmd.description,
+                cmd.deprecated,
+                &cmd.parameters,
+            );
+            self.message(
+                &mut body,
+                &format!("{}Returns", name),
+                &cmd.description,
+                cmd.deprecated,
+                &cmd.returns,
+            );
+        }
+        for evt in &domain.events {
+            self.message(
+                &mut body,
+                &format!("{}Event", pascal_case(&evt.name)),
+                &evt.description,
+                evt.deprecated,
+                &evt.parameters,
+            );
+        }
+
+        let mut source = String::new();
+
+        writeln!(
+            source,
+            "// Generated from version {}.{} of the protocol.",
+            version.major, version.minor
+        )
+        .unwrap();
+        writeln!(source, "syntax = \"proto3\";\n").unwrap();
+        comment(&mut source, "", &domain.description);
+        writeln!(source, "package {};\n", package).unwrap();
+
+        if !self.imports.is_empty() {
+            for import in &self.imports {
+                writeln!(source, "import \"{}\";", import).unwrap();
+            }
+            writeln!(source).unwrap();
+        }
+
+        source.push_str(body.trim_end());
+        source.push('\n');
+
+        ProtoFile {
+            path: format!("{}.proto", package.replace('.', "/")),
+            package,
+            source,
+        }
+    }
+
+    fn type_def(&mut self, out: &mut String, ty: &'a TypeDef<'a>) {
+        match ty.item {
+            Some(Item::Enum(ref variants)) => {
+                enumeration(out, "", &ty.id, &ty.description, ty.deprecated, variants);
+            }
+            Some(Item::Properties(ref props)) => {
+                self.message(out, &ty.id, &ty.description, ty.deprecated, props);
+            }
+            None => {
+                if let Type::ArrayOf(ref items) = ty.extends {
+                    let items = Kind::Repeated(Box::new(self.kind(self.domain, items, 0)));
+
+                    comment(out, "", &ty.description);
+                    writeln!(out, "message {} {{", ty.id).unwrap();
+                    deprecated_option(out, ty.deprecated);
+                    field(out, "items", None, items, items_number(), &self.names);
+                    writeln!(out, "}}\n").unwrap();
+                }
+                // other types are inlined where they are used
+            }
+        }
+    }
+
+    fn message(
+        &mut self,
+        out: &mut String,
+        name: &str,
+        description: &Description,
+        deprecated: bool,
+        params: &'a [Param<'a>],
+    ) {
+        let numbers = numbers(params.iter().map(|param| param.name.as_ref()), FIELD_MAX);
+
+        comment(out, "", description);
+        writeln!(out, "message {} {{", name).unwrap();
+        deprecated_option(out, deprecated);
+
+        for param in params {
+            let kind = self.kind(self.domain, &param.ty, 0);
+
+            field(
+                out,
+                &param.name,
+                Some(param),
+                kind,
+                numbers[param.name.as_ref()],
+                &self.names,
+            );
+        }
+
+        writeln!(out, "}}\n").unwrap();
+    }
+
+    /// How `ty`, used in `domain`, is spelled in a field.
+    fn kind(&mut self, domain: &'a Domain<'a>, ty: &'a Type<'a>, depth: usize) -> Kind<'a> {
+        match ty {
+            Type::Integer => Kind::Named("int32".into()),
+            Type::Number => Kind::Named("double".into()),
+            Type::Boolean => Kind::Named("bool".into()),
+            Type::String => Kind::Named("string".into()),
+            Type::Binary => Kind::Named("bytes".into()),
+            Type::Any | Type::Object => {
+                self.imports.insert(STRUCT_PROTO.into());
+
+                Kind::Named(VALUE.into())
+            }
+            Type::Enum(variants) => Kind::Enum(variants),
+            Type::ArrayOf(items) => Kind::Repeated(Box::new(self.kind(domain, items, depth))),
+            Type::Ref(id) => {
+                let (domain_name, name) = split(&domain.name, id);
+
+                match self.symbols.get(domain_name, name) {
+                    // an alias of a scalar type, which proto has no name for
+                    Some((domain, ty))
+                        if ty.item.is_none()
+                            && !matches!(ty.extends, Type::ArrayOf(_))
+                            && depth < 8 =>
+                    {
+                        self.kind(domain, &ty.extends, depth + 1)
+                    }
+                    Some((domain, ty)) => Kind::Named(self.qualified(&domain.name, &ty.id)),
+                    None => Kind::Named(self.qualified(domain_name, name)),
+                }
+            }
+        }
+    }
+
+    fn qualified(&mut self, domain: &str, name: &str) -> String {
+        if domain == self.domain.name {
+            name.to_owned()
+        } else {
+            let package = domain_package(self.package, domain);
+
+            self.imports
+                .insert(format!("{}.proto", package.replace('.', "/")));
+
+            format!(".{}.{}", package, name)
+        }
+    }
+}
+
+/// Write the field `name` of a `param`, or the `items` of an array without one.
+fn field(
+    out: &mut String,
+    name: &str,
+    param: Option<&Param>,
+    kind: Kind,
+    number: u32,
+    names: &HashSet<String>,
+) {
+    let (label, kind) = match kind {
+        Kind::Repeated(kind) => ("repeated ", *kind),
+        kind if param.is_some_and(|param| param.optional) => ("optional ", kind),
+        kind => ("", kind),
+    };
+    let ty = match kind {
+        Kind::Named(ty) => ty,
+        Kind::Enum(variants) => {
+            let mut ty = pascal_case(name);
+
+            if names.contains(&ty) {
+                ty.push_str("Enum");
+            }
+
+            enumeration(out, "  ", &ty, &Description::default(), false, variants);
+            ty
+        }
+        // proto has no repeated repeated fields
+        kind @ Kind::Repeated(_) => {
+            let ty = format!("{}Item", pascal_case(name));
+            let mut message = String::new();
+
+            writeln!(message, "message {} {{", ty).unwrap();
+            field(&mut message, "items", None, kind, items_number(), names);
+            writeln!(message, "}}").unwrap();
+
+            for line in message.lines() {
+                writeln!(out, "  {}", line).unwrap();
+            }
+
+            ty
+        }
+    };
+
+    if let Some(param) = param {
+        comment(out, "  ", &param.description);
+    }
+
+    write!(out, "  {}{} {} = {}", label, ty, name, number).unwrap();
+
+    if param.is_some_and(|param| param.deprecated) {
+        out.push_str(" [deprecated = true]");
+    }
+
+    out.push_str(";\n");
+}
+
+fn enumeration(
+    out: &mut String,
+    indent: &str,
+    name: &str,
+    description: &Description,
+    deprecated: bool,
+    variants: &[Variant],
+) {
+    let prefix = screaming_snake_case(name);
+    let numbers = numbers(
+        variants.iter().map(|variant| variant.name.as_ref()),
+        i32::MAX as u32,
+    );
+    let mut names = HashSet::new();
+
+    names.insert("UNSPECIFIED".to_owned());
+
+    comment(out, indent, description);
+    writeln!(out, "{}enum {} {{", indent, name).unwrap();
+
+    if deprecated {
+        writeln!(out, "{}  option deprecated = true;", indent).unwrap();
+    }
+
+    writeln!(out, "{}  {}_UNSPECIFIED = 0;", indent, prefix).unwrap();
+
+    for variant in variants {
+        let base = screaming_snake_case(&variant.name);
+        let mut value = base.clone();
+
+        // variants only differing in case or separators, like `foo-bar` and `fooBar`
+        for n in 2.. {
+            if names.insert(value.clone()) {
+                break;
+            }
+            value = format!("{}_{}", base, n);
+        }
+
+        comment(out, &format!("{}  ", indent), &variant.description);
+        writeln!(
+            out,
+            "{}  {}_{} = {};",
+            indent,
+            prefix,
+            value,
+            numbers[variant.name.as_ref()]
+        )
+        .unwrap();
+    }
+
+    writeln!(out, "{}}}", indent).unwrap();
+
+    if indent.is_empty() {
+        writeln!(out).unwrap();
+    }
+}
+
+fn deprecated_option(out: &mut String, deprecated: bool) {
+    if deprecated {
+        out.push_str("  option deprecated = true;\n");
+    }
+}
+
+fn comment(out: &mut String, indent: &str, description: &Description) {
+    for line in description.iter() {
+        if line.is_empty() {
+            writeln!(out, "{}//", indent).unwrap();
+        } else {
+            writeln!(out, "{}// {}", indent, line).unwrap();
+        }
+    }
+}
+
+/// The largest field number.
+const FIELD_MAX: u32 = 536_870_911;
+
+/// Number the `names` of the fields of a message or the values of an enum in `1..=max`.
+///
+/// Numbers come from the FNV-1a hash of the name, so a field keeps its number wherever
+/// it is declared. The names are numbered in alphabetical order, so when their hashes collide
+/// the numbers still don't depend on the order the names are declared in.
+fn numbers<'n, I>(names: I, max: u32) -> HashMap<&'n str, u32>
+where
+    I: IntoIterator<Item = &'n str>,
+{
+    let mut taken = HashSet::new();
+
+    names
+        .into_iter()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .map(|name| (name, number(name, max, &mut taken)))
+        .collect()
+}
+
+/// The number of the `items` field of the message of an array.
+fn items_number() -> u32 {
+    number("items", FIELD_MAX, &mut HashSet::new())
+}
+
+/// The first number in `1..=max` derived from `name` which isn't taken yet.
+///
+/// The numbers reserved for the implementation of protobuf are skipped.
+fn number(name: &str, max: u32, taken: &mut HashSet<u32>) -> u32 {
+    const RESERVED: std::ops::RangeInclusive<u32> = 19_000..=19_999;
+    const PRIME: u32 = 0x0100_0193;
+
+    let mut hash = name.bytes().fold(0x811c_9dc5_u32, |hash, b| {
+        (hash ^ u32::from(b)).wrapping_mul(PRIME)
+    });
+
+    loop {
+        let n = 1 + hash % max;
+
+        if !RESERVED.contains(&n) && taken.insert(n) {
+            return n;
+        }
+
+        hash = (hash ^ 0xff).wrapping_mul(PRIME);
+    }
+}
+
+fn pascal_case(name: &str) -> String {
+    let mut chars = name.chars();
+
+    chars
+        .next()
+        .map(|c| c.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+/// `CSSTransition` and `css-transition` both become `CSS_TRANSITION`.
+fn screaming_snake_case(name: &str) -> String {
+    let chars = name.chars().collect::<Vec<_>>();
+    let mut s = String::new();
+
+    for (i, &c) in chars.iter().enumerate() {
+        if !c.is_ascii_alphanumeric() {
+            if !s.is_empty() && !s.ends_with('_') {
+                s.push('_');
+            }
+            continue;
+        }
+
+        let prev = if i > 0 { chars[i - 1] } else { '_' };
+        let next = chars.get(i + 1).cloned().unwrap_or('_');
+        let boundary = c.is_ascii_uppercase()
+            && (prev.is_ascii_lowercase()
+                || prev.is_ascii_digit()
+                || (prev.is_ascii_uppercase() && next.is_ascii_lowercase()));
+
+        if boundary && !s.is_empty() && !s.ends_with('_') {
+            s.push('_');
+        }
+
+        s.push(c.to_ascii_uppercase());
+    }
+
+    if s.ends_with('_') {
+        s.pop();
+    }
+
+    s
+}
+
+#[cfg(all(test, feature = "parse"))]
+mod tests {
+    use super::*;
+
+    const PDL: &str = r#"version
+  major 1
+  minor 3
+
+domain Network
+  type LoaderId extends string
+
+  type Headers extends object
+
+domain Page
+  depends on Network
+
+  # Unique frame identifier.
+  type FrameId extends string
+
+  type TransitionType extends string
+    enum
+      link
+      auto_toplevel
+      CSSTransition
+
+  type Rect extends array of number
+
+  # Information about the Frame on the page.
+  type Frame extends object
+    properties
+      FrameId id
+      optional Network.LoaderId loaderId
+      deprecated optional array of Rect rects
+      Network.Headers headers
+
+  # Capture page screenshot.
+  deprecated command captureScreenshot
+    parameters
+      # Image compression format.
+      optional enum format
+        jpeg
+        png
+    returns
+      binary data
+
+  event frameNavigated
+    parameters
+      Frame frame
+      TransitionType type
+      any payload
+"#;
+
+    #[test]
+    fn proto() {
+        let files = crate::parse_str(PDL).unwrap().to_proto("cdp");
+
+        assert_eq!(files[1].path, "cdp/page.proto");
+        assert_eq!(files[1].package, "cdp.page");
+        assert_eq!(
+            files[1].source,
+            r#"// Generated from version 1.3 of the protocol.
+syntax = "proto3";
+
+package cdp.page;
+
+import "google/protobuf/struct.proto";
+
+enum TransitionType {
+  TRANSITION_TYPE_UNSPECIFIED = 0;
+  TRANSITION_TYPE_LINK = 232457834;
+  TRANSITION_TYPE_AUTO_TOPLEVEL = 1404850481;
+  TRANSITION_TYPE_CSS_TRANSITION = 1148335364;
+}
+
+message Rect {
+  repeated double items = 444150673;
+}
+
+// Information about the Frame on the page.
+message Frame {
+  string id = 389573346;
+  optional string loaderId = 167779476;
+  repeated Rect rects = 426110366 [deprecated = true];
+  google.protobuf.Value headers = 375773680;
+}
+
+// Capture page screenshot.
+message CaptureScreenshotParams {
+  option deprecated = true;
+  enum Format {
+    FORMAT_UNSPECIFIED = 0;
+    FORMAT_JPEG = 1054839589;
+    FORMAT_PNG = 1748353693;
+  }
+  // Image compression format.
+  optional Format format = 429753688;
+}
+
+// Capture page screenshot.
+message CaptureScreenshotReturns {
+  option deprecated = true;
+  bytes data = 410182316;
+}
+
+message FrameNavigatedEvent {
+  Frame frame = 302674349;
+  TransitionType type = 287830352;
+  google.protobuf.Value payload = 319780775;
+}
+"#
+        );
+    }
+
+    #[test]
+    fn proto_cross_domain() {
+        let files = crate::parse_str(&PDL.replace("  type Headers extends object\n", ""))
+            .unwrap()
+            .to_proto("cdp");
+
+        assert!(files[1].source.contains(
+            "\nimport \"cdp/network.proto\";\nimport \"google/protobuf/struct.proto\";\n"
+        ));
+        assert!(files[1]
+            .source
+            .contains("\n  .cdp.network.Headers headers = 375773680;\n"));
+    }
+
+    #[test]
+    fn proto_numbers_are_stable() {
+        let numbers = |pdl: &str| {
+            let files = crate::parse_str(pdl).unwrap().to_proto("cdp");
+
+            files[1]
+                .source
+                .lines()
+                .filter_map(|line| {
+                    let (decl, number) = line.trim_end_matches(';').split_at(line.find(" = ")?);
+
+                    Some((decl.trim().to_owned(), number[3..].to_owned()))
+                })
+                .collect::<Vec<_>>()
+        };
+        let before = numbers(PDL);
+        let after = numbers(
+            &PDL.replace(
+                "      FrameId id\n",
+                "      string name\n      FrameId id\n",
+            )
+            .replace("      link\n", "      reload\n      link\n"),
+        );
+
+        assert_eq!(after.len(), before.len() + 2);
+        assert!(before.iter().all(|number| after.contains(number)));
+    }
+
+    #[test]
+    fn proto_numbers_collide() {
+        let source = |fields: &str| {
+            let pdl = format!(
+                "version\n  major 1\n  minor 3\n\ndomain Page\n  event loaded\n    parameters\n{}",
+                fields
+            );
+
+            crate::parse_str(&pdl).unwrap().to_proto("cdp")[0]
+                .source
+                .clone()
+        };
+        let first = source("      string field25892\n      string field68860\n");
+        let second = source("      string field68860\n      string field25892\n");
+
+        // both names hash to 420561066
+        assert!(first.contains("  string field25892 = 420561066;\n"));
+        assert!(first.contains("  string field68860 = "));
+        assert!(!first.contains("  string field68860 = 420561066;\n"));
+        assert_eq!(
+            first.lines().collect::<HashSet<_>>(),
+            second.lines().collect::<HashSet<_>>()
+        );
+    }
+
+    #[test]
+    fn proto_nested_enum_shadowing() {
+        let files = crate::parse_str(
+            r#"version
+  major 1
+  minor 3
+
+domain Page
+  type Format extends string
+    enum
+      jpeg
+
+  command captureScreenshot
+    parameters
+      Format preferred
+      optional enum format
+        png
+"#,
+        )
+        .unwrap()
+        .to_proto("cdp");
+
+        assert!(files[0].source.contains(
+            r#"message CaptureScreenshotParams {
+  Format preferred = 219968673;
+  enum FormatEnum {
+    FORMAT_ENUM_UNSPECIFIED = 0;
+    FORMAT_ENUM_PNG = 1748353693;
+  }
+  optional FormatEnum format = 429753688;
+}
+"#
+        ));
+    }
+
+    #[cfg(feature = "from_json")]
+    #[test]
+    fn proto_nested_arrays() {
+        let protocol = Protocol::from_json(
+            r#"{
+                "version": { "major": "1", "minor": "3" },
+                "domains": [{
+                    "domain": "DOM",
+                    "types": [
+                        { "id": "Quad", "type": "array", "items": { "type": "array", "items": { "type": "number" } } }
+                    ]
+                }]
+            }"#,
+        )
+        .unwrap();
+
+        assert!(protocol.to_proto("cdp")[0].source.ends_with(
+            r#"
+message Quad {
+  message ItemsItem {
+    repeated double items = 444150673;
+  }
+  repeated ItemsItem items = 444150673;
+}
+"#
+        ));
+    }
+
+    #[test]
+    fn proto_enum_collisions() {
+        let files = crate::parse_str(
+            r#"version
+  major 1
+  minor 3
+
+domain CSS
+  type Display extends string
+    enum
+      inline-block
+      inlineBlock
+      inline_block
+      unspecified
+"#,
+        )
+        .unwrap()
+        .to_proto("cdp");
+
+        assert_eq!(
+            files[0]
+                .source
+                .lines()
+                .filter_map(|line| Some(line.trim().split(" = ").next()?.to_owned()))
+                .filter(|line| line.starts_with("DISPLAY_"))
+                .collect::<Vec<_>>(),
+            vec![
+                "DISPLAY_UNSPECIFIED",
+                "DISPLAY_INLINE_BLOCK",
+                "DISPLAY_INLINE_BLOCK_2",
+                "DISPLAY_INLINE_BLOCK_3",
+                "DISPLAY_UNSPECIFIED_2",
+            ]
+        );
+    }
+
+    #[test]
+    fn screaming_snake_case_names() {
+        assert_eq!(screaming_snake_case("CSSTransition"), "CSS_TRANSITION");
+        assert_eq!(screaming_snake_case("auto_toplevel"), "AUTO_TOPLEVEL");
+        assert_eq!(screaming_snake_case("x-www-form"), "X_WWW_FORM");
+        assert_eq!(
+            screaming_snake_case("backendDOMNodeId"),
+            "BACKEND_DOM_NODE_ID"
+        );
+        assert_eq!(screaming_snake_case("-webkit-box"), "WEBKIT_BOX");
+    }
+}
@@ -0,0 +1,263 @@
+use std::{cmp, ptr};
+
+use crate::resolve::{split, Owner};
+use crate::*;
+
+/// The type definitions of a `Protocol` which refer to themselves, directly or through other types.
+///
+/// The types are the nodes of a graph, with an edge for every reference in the type they extend
+/// or in their properties, including those in an `array of`. A strongly connected component
+/// of more than one type, or of a type referring to itself, is a cycle.
+#[derive(Clone, Debug, Default)]
+pub struct RecursiveTypes<'p, 'a> {
+    symbols: SymbolTable<'p, 'a>,
+    /// The cycle of every type, if it is part of one.
+    cycles: Vec<Option<usize>>,
+    components: Vec<Vec<(&'p Domain<'a>, &'p TypeDef<'a>)>>,
+}
+
+impl<'p, 'a> RecursiveTypes<'p, 'a> {
+    /// Find the cycles between the type definitions of the `protocol`, as resolved by its `SymbolTable`.
+    pub fn new(protocol: &'p Protocol<'a>) -> Self {
+        let symbols = protocol.symbols();
+        let types = symbols.iter().collect::<Vec<_>>();
+        let mut edges = vec![vec![]; types.len()];
+
+        for domain in &protocol.domains {
+            for ty in &domain.types {
+                // a type defined twice is known by its first definition only
+                let v = match symbols.index(&domain.name, &ty.id) {
+                    Some(v) if ptr::eq(types[v].1, ty) => v,
+                    _ => continue,
+                };
+
+                edges[v] = Owner::Type(ty)
+                    .references(domain)
+                    .filter_map(|item_ref| {
+                        let (domain, id) = split(&domain.name, item_ref.reference);
+
+                        symbols.index(domain, id)
+                    })
+                    .collect();
+            }
+        }
+
+        let mut tarjan = Tarjan {
+            edges: &edges,
+            indexes: vec![None; types.len()],
+            lowlinks: vec![0; types.len()],
+            on_stack: vec![false; types.len()],
+            stack: vec![],
+            next: 0,
+            components: vec![],
+        };
+
+        for v in 0..types.len() {
+            if tarjan.indexes[v].is_none() {
+                tarjan.connect(v);
+            }
+        }
+
+        let mut components = tarjan
+            .components
+            .into_iter()
+            .filter(|component| component.len() > 1 || edges[component[0]].contains(&component[0]))
+            .map(|mut component| {
+                component.sort_unstable();
+                component
+            })
+            .collect::<Vec<_>>();
+
+        components.sort_unstable_by_key(|component| component[0]);
+
+        let mut cycles = vec![None; types.len()];
+
+        for (i, component) in components.iter().enumerate() {
+            for &v in component {
+                cycles[v] = Some(i);
+            }
+        }
+
+        RecursiveTypes {
+            symbols,
+            cycles,
+            components: components
+                .into_iter()
+                .map(|component| component.into_iter().map(|v| types[v]).collect())
+                .collect(),
+        }
+    }
+
+    /// The cycles, each with its types in the order they are defined.
+    pub fn components(&self) -> &[Vec<(&'p Domain<'a>, &'p TypeDef<'a>)>] {
+        &self.components
+    }
+
+    /// Whether the type definition `id` of the domain named `domain` is part of a cycle.
+    pub fn is_recursive(&self, domain: &str, id: &str) -> bool {
+        self.cycle(domain, id).is_some()
+    }
+
+    /// Whether the property `prop` of the type definition `id` of the domain named `domain`
+    /// refers back to it, so a generator has to add an indirection somewhere along the cycle.
+    pub fn is_recursive_property(&self, domain: &str, id: &str, prop: &Param) -> bool {
+        let cycle = match self.cycle(domain, id) {
+            Some(cycle) => cycle,
+            None => return false,
+        };
+        prop.ty.references().any(|reference| {
+            let (domain, id) = split(domain, reference);
+
+            self.cycle(domain, id) == Some(cycle)
+        })
+    }
+
+    fn cycle(&self, domain: &str, id: &str) -> Option<usize> {
+        self.symbols.index(domain, id).and_then(|v| self.cycles[v])
+    }
+}
+
+impl<'a> Protocol<'a> {
+    /// Find the type definitions which refer to themselves, directly or through other types.
+    pub fn recursive_types(&self) -> RecursiveTypes<'_, 'a> {
+        RecursiveTypes::new(self)
+    }
+}
+
+/// Tarjan's algorithm, finding the strongly connected components of a graph.
+struct Tarjan<'e> {
+    edges: &'e [Vec<usize>],
+    indexes: Vec<Option<usize>>,
+    lowlinks: Vec<usize>,
+    on_stack: Vec<bool>,
+    stack: Vec<usize>,
+    next: usize,
+    components: Vec<Vec<usize>>,
+}
+
+impl Tarjan<'_> {
+    fn connect(&mut self, v: usize) {
+        self.indexes[v] = Some(self.next);
+        self.lowlinks[v] = self.next;
+        self.next += 1;
+        self.stack.push(v);
+        self.on_stack[v] = true;
+
+        for &w in self.edges[v].iter() {
+            match self.indexes[w] {
+                None => {
+                    self.connect(w);
+                    self.lowlinks[v] = cmp::min(self.lowlinks[v], self.lowlinks[w]);
+                }
+                Some(index) if self.on_stack[w] => {
+                    self.lowlinks[v] = cmp::min(self.lowlinks[v], index);
+                }
+                Some(_) => {}
+            }
+        }
+
+        if Some(self.lowlinks[v]) == self.indexes[v] {
+            let mut component = vec![];
+
+            while let Some(w) = self.stack.pop() {
+                self.on_stack[w] = false;
+                component.push(w);
+
+                if w == v {
+                    break;
+                }
+            }
+
+            self.components.push(component);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "parse"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recursive_types() {
+        let protocol = crate::parse_str(
+            r#"version
+  major 1
+  minor 3
+
+domain Runtime
+  type CallFrame extends object
+    properties
+      string functionName
+
+  type StackTrace extends object
+    properties
+      array of CallFrame callFrames
+      optional StackTrace parent
+
+domain DOM
+  depends on Runtime
+
+  type NodeId extends integer
+
+  type Node extends object
+    properties
+      NodeId nodeId
+      optional array of Node children
+      optional Runtime.StackTrace stackTrace
+
+  type Rule extends object
+    properties
+      Style style
+
+  type Style extends object
+    properties
+      optional Rules rules
+
+  type Rules extends array of Rule
+"#,
+        )
+        .unwrap();
+        let recursive = protocol.recursive_types();
+
+        assert_eq!(
+            recursive
+                .components()
+                .iter()
+                .map(|component| component
+                    .iter()
+                    .map(|(domain, ty)| format!("{}.{}", domain.name, ty.id))
+                    .collect::<Vec<_>>())
+                .collect::<Vec<_>>(),
+            vec![
+                vec!["Runtime.StackTrace"],
+                vec!["DOM.Node"],
+                vec!["DOM.Rule", "DOM.Style", "DOM.Rules"],
+            ]
+        );
+
+        assert!(recursive.is_recursive("Runtime", "StackTrace"));
+        assert!(!recursive.is_recursive("Runtime", "CallFrame"));
+        assert!(!recursive.is_recursive("DOM", "NodeId"));
+        assert!(recursive.is_recursive("DOM", "Rules"));
+
+        let cyclic = |domain: &str, id: &str| match protocol.resolve(domain, id) {
+            Some((
+                _,
+                TypeDef {
+                    item: Some(Item::Properties(props)),
+                    ..
+                },
+            )) => props
+                .iter()
+                .filter(|prop| recursive.is_recursive_property(domain, id, prop))
+                .map(|prop| prop.name.as_ref())
+                .collect::<Vec<_>>(),
+            _ => vec![],
+        };
+
+        assert_eq!(cyclic("Runtime", "StackTrace"), vec!["parent"]);
+        assert_eq!(cyclic("DOM", "Node"), vec!["children"]);
+        assert_eq!(cyclic("DOM", "Style"), vec!["rules"]);
+        assert_eq!(cyclic("Runtime", "CallFrame"), Vec::<&str>::new());
+    }
+}
@@ -0,0 +1,375 @@
+use std::collections::HashMap;
+
+use crate::*;
+
+/// The type definitions of a `Protocol` by domain and id, to resolve many `Type::Ref`s.
+#[derive(Clone, Debug, Default)]
+pub struct SymbolTable<'p, 'a> {
+    types: Vec<(&'p Domain<'a>, &'p TypeDef<'a>)>,
+    indexes: HashMap<(&'p str, &'p str), usize>,
+}
+
+impl<'p, 'a> SymbolTable<'p, 'a> {
+    /// Collect the type definitions of the `protocol`.
+    ///
+    /// When a domain defines a type more than once, the first definition wins.
+    pub fn new(protocol: &'p Protocol<'a>) -> Self {
+        let mut types = vec![];
+        let mut indexes = HashMap::new();
+
+        for domain in &protocol.domains {
+            for ty in &domain.types {
+                indexes
+                    .entry((domain.name.as_ref(), ty.id.as_ref()))
+                    .or_insert_with(|| {
+                        types.push((domain, ty));
+                        types.len() - 1
+                    });
+            }
+        }
+
+        SymbolTable { types, indexes }
+    }
+
+    /// The type definition `id` of the domain named `domain`.
+    pub fn get(&self, domain: &str, id: &str) -> Option<(&'p Domain<'a>, &'p TypeDef<'a>)> {
+        self.index(domain, id).map(|i| self.types[i])
+    }
+
+    /// The type definitions, in the order they are defined.
+    pub fn iter(&self) -> impl Iterator<Item = (&'p Domain<'a>, &'p TypeDef<'a>)> + '_ {
+        self.types.iter().cloned()
+    }
+
+    /// The position in `iter` of the type definition `id` of the domain named `domain`.
+    pub(crate) fn index(&self, domain: &str, id: &str) -> Option<usize> {
+        self.indexes.get(&(domain, id)).cloned()
+    }
+
+    /// Resolve the `reference` of a `Type::Ref` used in the domain named `domain`.
+    pub fn resolve(
+        &self,
+        domain: &str,
+        reference: &str,
+    ) -> Option<(&'p Domain<'a>, &'p TypeDef<'a>)> {
+        let (domain, id) = split(domain, reference);
+
+        self.get(domain, id)
+    }
+
+    /// The number of type definitions.
+    pub fn len(&self) -> usize {
+        self.types.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.types.is_empty()
+    }
+}
+
+/// Split a reference used in `domain` into the domain and id of the type it refers to.
+///
+/// A reference is either qualified like `Network.RequestId`, or local to `domain` like `FrameId`.
+pub(crate) fn split<'r>(domain: &'r str, reference: &'r str) -> (&'r str, &'r str) {
+    match reference.find('.') {
+        Some(pos) => (&reference[..pos], &reference[pos + 1..]),
+        None => (domain, reference),
+    }
+}
+
+impl<'a> Type<'a> {
+    /// The type of the elements of an `array of`, through nested arrays, or the type itself.
+    pub fn element(&self) -> &Type<'a> {
+        match self {
+            Type::ArrayOf(ty) => ty.element(),
+            ty => ty,
+        }
+    }
+
+    /// The references to type definitions the type makes, through `array of`.
+    pub fn references(&self) -> impl Iterator<Item = &str> {
+        match self.element() {
+            Type::Ref(reference) => Some(reference.as_ref()),
+            _ => None,
+        }
+        .into_iter()
+    }
+}
+
+/// An item of a domain, making type references.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Owner<'p, 'a> {
+    Type(&'p TypeDef<'a>),
+    Command(&'p Command<'a>),
+    Event(&'p Event<'a>),
+}
+
+/// Where in an item a type reference is made.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Site<'p, 'a> {
+    /// The type a type definition extends.
+    Extends,
+    Property(&'p Param<'a>),
+    Parameter(&'p Param<'a>),
+    Return(&'p Param<'a>),
+}
+
+/// A type reference made by an item of a domain.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ItemReference<'p, 'a> {
+    pub domain: &'p Domain<'a>,
+    pub owner: Owner<'p, 'a>,
+    pub site: Site<'p, 'a>,
+    /// The reference of the `Type::Ref`, through `array of`.
+    pub reference: &'p str,
+}
+
+impl<'p, 'a> Owner<'p, 'a> {
+    /// The id of a type definition, or the name of a command or event.
+    pub fn name(&self) -> &'p str {
+        match self {
+            Owner::Type(ty) => &ty.id,
+            Owner::Command(cmd) => &cmd.name,
+            Owner::Event(evt) => &evt.name,
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            Owner::Type(ty) => ty.span,
+            Owner::Command(cmd) => cmd.span,
+            Owner::Event(evt) => evt.span,
+        }
+    }
+
+    /// The type references the item of `domain` makes, in the order they are written.
+    pub fn references(self, domain: &'p Domain<'a>) -> impl Iterator<Item = ItemReference<'p, 'a>> {
+        let (extends, params): (_, Vec<_>) = match self {
+            Owner::Type(ty) => (
+                Some(&ty.extends),
+                match ty.item {
+                    Some(Item::Properties(ref props)) => props.iter().map(Site::Property).collect(),
+                    _ => vec![],
+                },
+            ),
+            Owner::Command(cmd) => (
+                None,
+                cmd.parameters
+                    .iter()
+                    .map(Site::Parameter)
+                    .chain(cmd.returns.iter().map(Site::Return))
+                    .collect(),
+            ),
+            Owner::Event(evt) => (None, evt.parameters.iter().map(Site::Parameter).collect()),
+        };
+
+        extends
+            .into_iter()
+            .flat_map(|ty| ty.references().map(|reference| (Site::Extends, reference)))
+            .chain(params.into_iter().flat_map(|site| {
+                site.param()
+                    .into_iter()
+                    .flat_map(|param| param.ty.references())
+                    .map(move |reference| (site, reference))
+            }))
+            .map(move |(site, reference)| ItemReference {
+                domain,
+                owner: self,
+                site,
+                reference,
+            })
+    }
+}
+
+impl<'p, 'a> Site<'p, 'a> {
+    /// The property, parameter or return value making the reference.
+    pub fn param(&self) -> Option<&'p Param<'a>> {
+        match *self {
+            Site::Extends => None,
+            Site::Property(param) | Site::Parameter(param) | Site::Return(param) => Some(param),
+        }
+    }
+}
+
+impl<'p, 'a> ItemReference<'p, 'a> {
+    /// The span of the property or parameter making the reference, or of the type definition it extends.
+    pub fn span(&self) -> Span {
+        self.site
+            .param()
+            .map_or_else(|| self.owner.span(), |param| param.span)
+    }
+
+    /// Where the reference is made, like `Page.Frame` for the type it extends or `Page.Frame.loaderId` for a property.
+    pub fn label(&self) -> String {
+        match self.site.param() {
+            Some(param) => format!("{}.{}.{}", self.domain.name, self.owner.name(), param.name),
+            None => format!("{}.{}", self.domain.name, self.owner.name()),
+        }
+    }
+}
+
+impl<'a> Domain<'a> {
+    /// The type references made by the types, commands and events of the domain, in the order they are defined.
+    pub(crate) fn item_references(&self) -> impl Iterator<Item = ItemReference<'_, 'a>> {
+        self.types
+            .iter()
+            .map(Owner::Type)
+            .chain(self.commands.iter().map(Owner::Command))
+            .chain(self.events.iter().map(Owner::Event))
+            .flat_map(move |owner| owner.references(self))
+    }
+}
+
+impl<'a> Protocol<'a> {
+    /// Resolve the `reference` of a `Type::Ref` used in the domain named `domain` to its type definition.
+    ///
+    /// This looks the domain and type up, use a `SymbolTable` to resolve many references.
+    pub fn resolve(&self, domain: &str, reference: &str) -> Option<(&Domain<'a>, &TypeDef<'a>)> {
+        let (domain, id) = split(domain, reference);
+        let domain = self.domains.iter().find(|d| d.name == domain)?;
+        let ty = domain.types.iter().find(|ty| ty.id == id)?;
+
+        Some((domain, ty))
+    }
+
+    /// Collect the type definitions to resolve many references.
+    pub fn symbols(&self) -> SymbolTable<'_, 'a> {
+        SymbolTable::new(self)
+    }
+}
+
+#[cfg(all(test, feature = "parse"))]
+mod tests {
+    use super::*;
+
+    const PDL: &str = r#"version
+  major 1
+  minor 3
+
+domain Network
+  type RequestId extends string
+
+domain Page
+  depends on Network
+
+  type FrameId extends string
+
+  event frameRequested
+    parameters
+      FrameId frameId
+      Network.RequestId requestId
+      Runtime.RemoteObject object
+"#;
+
+    #[test]
+    fn resolve() {
+        let protocol = crate::parse_str(PDL).unwrap();
+        let resolved = |domain, reference| {
+            protocol
+                .resolve(domain, reference)
+                .map(|(domain, ty)| format!("{}.{}", domain.name, ty.id))
+        };
+
+        assert_eq!(resolved("Page", "FrameId"), Some("Page.FrameId".into()));
+        assert_eq!(
+            resolved("Page", "Network.RequestId"),
+            Some("Network.RequestId".into())
+        );
+        assert_eq!(
+            resolved("Network", "RequestId"),
+            Some("Network.RequestId".into())
+        );
+        assert_eq!(resolved("Network", "FrameId"), None);
+        assert_eq!(resolved("Page", "Runtime.RemoteObject"), None);
+    }
+
+    #[test]
+    fn symbol_table() {
+        let protocol = crate::parse_str(PDL).unwrap();
+        let symbols = protocol.symbols();
+        let event = &protocol.domains[1].events[0];
+
+        assert_eq!(symbols.len(), 2);
+
+        let resolved = event
+            .parameters
+            .iter()
+            .map(|param| match param.ty {
+                Type::Ref(ref reference) => symbols
+                    .resolve("Page", reference)
+                    .map(|(domain, ty)| (domain.name.as_ref(), ty.id.as_ref())),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            resolved,
+            vec![
+                Some(("Page", "FrameId")),
+                Some(("Network", "RequestId")),
+                None
+            ]
+        );
+        assert_eq!(
+            symbols.get("Page", "FrameId").map(|(_, ty)| ty.span.line),
+            Some(11)
+        );
+    }
+
+    #[test]
+    fn item_references() {
+        let protocol = crate::parse_str(
+            r#"version
+  major 1
+  minor 3
+
+domain Page
+  type Frames extends array of Frame
+
+  type Frame extends object
+    properties
+      string url
+      Network.LoaderId loaderId
+
+  command navigate
+    parameters
+      Frame frame
+    returns
+      array of Network.RequestId requestIds
+
+  event frameAttached
+    parameters
+      Frame frame
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            protocol.domains[0]
+                .item_references()
+                .map(|item_ref| (item_ref.label(), item_ref.reference, item_ref.span().line))
+                .collect::<Vec<_>>(),
+            vec![
+                ("Page.Frames".into(), "Frame", 6),
+                ("Page.Frame.loaderId".into(), "Network.LoaderId", 11),
+                ("Page.navigate.frame".into(), "Frame", 15),
+                ("Page.navigate.requestIds".into(), "Network.RequestId", 17),
+                ("Page.frameAttached.frame".into(), "Frame", 21),
+            ]
+        );
+    }
+
+    #[test]
+    fn type_references() {
+        let nested = Type::ArrayOf(Box::new(Type::ArrayOf(Box::new(Type::Ref(
+            "Network.RequestId".into(),
+        )))));
+
+        assert_eq!(
+            nested.references().collect::<Vec<_>>(),
+            vec!["Network.RequestId"]
+        );
+        assert_eq!(nested.element(), &Type::Ref("Network.RequestId".into()));
+        assert_eq!(Type::String.references().count(), 0);
+    }
+}
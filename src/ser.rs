@@ -5,6 +5,10 @@ use serde::{
 
 use crate::*;
 
+mod compat;
+
+use self::compat::{Compat, PythonFormatter};
+
 pub fn serialize_usize<S>(n: &usize, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
@@ -16,7 +20,8 @@ pub fn serialize_description<S>(description: &Description, serializer: S) -> Res
 where
     S: Serializer,
 {
-    serializer.serialize_str(&description.0.join(" "))
+    // the lines are joined by spaces as they always were, `to_chromium_json` keeps them apart
+    serializer.serialize_str(&description.join(" "))
 }
 
 pub fn serialize_enum<S>(variants: &[Variant], serializer: S) -> Result<S::Ok, S::Error>
@@ -26,23 +31,12 @@ where
     let mut seq = serializer.serialize_seq(Some(variants.len()))?;
 
     for variant in variants {
-        seq.serialize_element(variant.name)?;
+        seq.serialize_element(&variant.name)?;
     }
 
     seq.end()
 }
 
-pub fn serialize_redirect<S>(redirect: &Option<Redirect>, serializer: S) -> Result<S::Ok, S::Error>
-where
-    S: Serializer,
-{
-    if let Some(redirect) = redirect {
-        serializer.serialize_str(&redirect.to)
-    } else {
-        serializer.serialize_none()
-    }
-}
-
 pub fn is_false(v: &bool) -> bool {
     !*v
 }
@@ -57,6 +51,54 @@ impl Protocol<'_> {
     pub fn to_json_pretty(&self) -> serde_json::Result<String> {
         serde_json::to_string_pretty(self)
     }
+
+    /// Serialize the `Protocol` data structure as a String of JSON, byte for byte like
+    /// the `pdl_to_json` converter of Chromium does.
+    ///
+    /// Only what Chromium's format has a slot for is kept, e.g. annotations and
+    /// the comments of enum variants and redirects are left out.
+    ///
+    /// The `types`, `commands` and `events` of a domain come in the order their first
+    /// item appears in the source, as the converter adds them. A protocol built
+    /// without spans, e.g. loaded from JSON, lists them in that order.
+    pub fn to_chromium_json(&self) -> serde_json::Result<String> {
+        let mut buf = vec![];
+
+        self.as_chromium()
+            .serialize(&mut serde_json::Serializer::with_formatter(
+                &mut buf,
+                PythonFormatter::new(),
+            ))?;
+
+        Ok(String::from_utf8(buf).expect("non-ASCII characters are escaped"))
+    }
+
+    /// The `Protocol` laid out like Chromium's JSON, to use with any `Serializer`.
+    pub fn as_chromium(&self) -> impl Serialize + '_ {
+        Compat(self)
+    }
+}
+
+impl Serialize for Dependency<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.name)
+    }
+}
+
+/// Flattened into the command, as Chromium has no object for a redirect, nor a slot for its comments.
+impl Serialize for Redirect<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(1))?;
+
+        map.serialize_entry("redirect", &self.to)?;
+        map.end()
+    }
 }
 
 impl Serialize for Type<'_> {
@@ -94,7 +136,7 @@ impl Serialize for Type<'_> {
                     "enum",
                     &variants
                         .iter()
-                        .map(|variant| variant.name)
+                        .map(|variant| variant.name.as_ref())
                         .collect::<Vec<_>>(),
                 )?;
             }
@@ -0,0 +1,564 @@
+use std::io;
+
+use serde::{
+    ser::{SerializeMap, SerializeSeq},
+    Serialize, Serializer,
+};
+use serde_json::ser::{Formatter, PrettyFormatter};
+
+use crate::*;
+
+/// A node serialized the way Chromium's `pdl_to_json` converter lays it out.
+///
+/// Keys follow the order the converter creates them in, and the comments Chromium
+/// has no slot for, like those of enum variants and redirects, are left out.
+pub(crate) struct Compat<'a, T: ?Sized>(pub &'a T);
+
+impl Serialize for Compat<'_, Protocol<'_>> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(None)?;
+
+        map.serialize_entry("version", &Compat(&self.0.version))?;
+        map.serialize_entry("domains", &Compat(self.0.domains.as_slice()))?;
+
+        map.end()
+    }
+}
+
+impl<'a, T> Serialize for Compat<'a, [T]>
+where
+    Compat<'a, T>: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
+
+        for node in self.0 {
+            seq.serialize_element(&Compat(node))?;
+        }
+
+        seq.end()
+    }
+}
+
+impl Serialize for Compat<'_, Version<'_>> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(None)?;
+
+        map.serialize_entry("major", &self.0.major.to_string())?;
+        map.serialize_entry("minor", &self.0.minor.to_string())?;
+
+        map.end()
+    }
+}
+
+impl Serialize for Compat<'_, Domain<'_>> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let domain = self.0;
+        let mut map = serializer.serialize_map(None)?;
+
+        map.serialize_entry("domain", &domain.name)?;
+        serialize_header(
+            &mut map,
+            &domain.description,
+            domain.experimental,
+            domain.deprecated,
+        )?;
+
+        if !domain.dependencies.is_empty() {
+            map.serialize_entry("dependencies", &domain.dependencies)?;
+        }
+
+        // the converter adds each list when it meets the first item of it
+        let mut lists = vec![];
+
+        if let Some(ty) = domain.types.first() {
+            lists.push((ty.span.start, "types"));
+        }
+        if let Some(cmd) = domain.commands.first() {
+            lists.push((cmd.span.start, "commands"));
+        }
+        if let Some(evt) = domain.events.first() {
+            lists.push((evt.span.start, "events"));
+        }
+
+        lists.sort_by_key(|&(start, _)| start);
+
+        for (_, key) in lists {
+            match key {
+                "types" => map.serialize_entry(key, &Compat(domain.types.as_slice()))?,
+                "commands" => map.serialize_entry(key, &Compat(domain.commands.as_slice()))?,
+                _ => map.serialize_entry(key, &Compat(domain.events.as_slice()))?,
+            }
+        }
+
+        map.end()
+    }
+}
+
+impl Serialize for Compat<'_, TypeDef<'_>> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let ty = self.0;
+        let mut map = serializer.serialize_map(None)?;
+
+        map.serialize_entry("id", &ty.id)?;
+        serialize_header(&mut map, &ty.description, ty.experimental, ty.deprecated)?;
+
+        match ty.item {
+            // the `enum` block holds the variants of an `enum` or `array of enum` it extends
+            Some(Item::Enum(ref variants)) => {
+                serialize_kind(&mut map, &ty.extends)?;
+                map.serialize_entry("enum", &Compat(variants.as_slice()))?;
+            }
+            Some(Item::Properties(ref props)) => {
+                serialize_type(&mut map, &ty.extends)?;
+                map.serialize_entry("properties", &Compat(props.as_slice()))?;
+            }
+            None => serialize_type(&mut map, &ty.extends)?,
+        }
+
+        map.end()
+    }
+}
+
+impl Serialize for Compat<'_, Variant<'_>> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.0.name)
+    }
+}
+
+impl Serialize for Compat<'_, Param<'_>> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let param = self.0;
+        let mut map = serializer.serialize_map(None)?;
+
+        map.serialize_entry("name", &param.name)?;
+        serialize_header(
+            &mut map,
+            &param.description,
+            param.experimental,
+            param.deprecated,
+        )?;
+
+        if param.optional {
+            map.serialize_entry("optional", &true)?;
+        }
+
+        serialize_type(&mut map, &param.ty)?;
+
+        map.end()
+    }
+}
+
+impl Serialize for Compat<'_, Command<'_>> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let cmd = self.0;
+        let mut map = serializer.serialize_map(None)?;
+
+        map.serialize_entry("name", &cmd.name)?;
+        serialize_header(&mut map, &cmd.description, cmd.experimental, cmd.deprecated)?;
+
+        if let Some(ref redirect) = cmd.redirect {
+            map.serialize_entry("redirect", &redirect.to)?;
+        }
+        if !cmd.parameters.is_empty() {
+            map.serialize_entry("parameters", &Compat(cmd.parameters.as_slice()))?;
+        }
+        if !cmd.returns.is_empty() {
+            map.serialize_entry("returns", &Compat(cmd.returns.as_slice()))?;
+        }
+
+        map.end()
+    }
+}
+
+impl Serialize for Compat<'_, Event<'_>> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let evt = self.0;
+        let mut map = serializer.serialize_map(None)?;
+
+        map.serialize_entry("name", &evt.name)?;
+        serialize_header(&mut map, &evt.description, evt.experimental, evt.deprecated)?;
+
+        if !evt.parameters.is_empty() {
+            map.serialize_entry("parameters", &Compat(evt.parameters.as_slice()))?;
+        }
+
+        map.end()
+    }
+}
+
+/// The `items` of an array.
+impl Serialize for Compat<'_, Type<'_>> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(None)?;
+
+        serialize_type(&mut map, self.0)?;
+
+        map.end()
+    }
+}
+
+/// Serialize the `description`, `experimental` and `deprecated` keys that follow the name of every node.
+fn serialize_header<M>(
+    map: &mut M,
+    description: &Description,
+    experimental: bool,
+    deprecated: bool,
+) -> Result<(), M::Error>
+where
+    M: SerializeMap,
+{
+    let description = description.joined();
+
+    if !description.trim().is_empty() {
+        map.serialize_entry("description", description.trim())?;
+    }
+    if experimental {
+        map.serialize_entry("experimental", &true)?;
+    }
+    if deprecated {
+        map.serialize_entry("deprecated", &true)?;
+    }
+
+    Ok(())
+}
+
+fn serialize_type<M>(map: &mut M, ty: &Type) -> Result<(), M::Error>
+where
+    M: SerializeMap,
+{
+    serialize_kind(map, ty)?;
+
+    // the converter puts the variants of an array of enum next to its `items`
+    match ty {
+        Type::Enum(variants) => map.serialize_entry("enum", &Compat(variants.as_slice())),
+        Type::ArrayOf(ty) => match **ty {
+            Type::Enum(ref variants) => map.serialize_entry("enum", &Compat(variants.as_slice())),
+            _ => Ok(()),
+        },
+        _ => Ok(()),
+    }
+}
+
+/// Serialize the `type`, `items` or `$ref` keys of a type, leaving out the variants of an enum.
+fn serialize_kind<M>(map: &mut M, ty: &Type) -> Result<(), M::Error>
+where
+    M: SerializeMap,
+{
+    match ty {
+        Type::Integer => map.serialize_entry("type", "integer"),
+        Type::Number => map.serialize_entry("type", "number"),
+        Type::Boolean => map.serialize_entry("type", "boolean"),
+        Type::String | Type::Enum(_) => map.serialize_entry("type", "string"),
+        Type::Object => map.serialize_entry("type", "object"),
+        Type::Any => map.serialize_entry("type", "any"),
+        Type::Binary => map.serialize_entry("type", "binary"),
+        Type::ArrayOf(ty) => {
+            map.serialize_entry("type", "array")?;
+
+            match **ty {
+                Type::Enum(_) => map.serialize_entry("items", &Compat(&Type::String)),
+                ref items => map.serialize_entry("items", &Compat(items)),
+            }
+        }
+        Type::Ref(id) => map.serialize_entry("$ref", id),
+    }
+}
+
+/// Format JSON like Python's `json.dump` with `indent=4`, escaping every non-ASCII character.
+pub(crate) struct PythonFormatter(PrettyFormatter<'static>);
+
+impl PythonFormatter {
+    pub fn new() -> Self {
+        PythonFormatter(PrettyFormatter::with_indent(b"    "))
+    }
+}
+
+impl Formatter for PythonFormatter {
+    fn write_string_fragment<W>(&mut self, writer: &mut W, fragment: &str) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        for (i, c) in fragment.char_indices() {
+            if c.is_ascii() {
+                writer.write_all(&fragment.as_bytes()[i..i + 1])?;
+            } else {
+                for unit in c.encode_utf16(&mut [0; 2]) {
+                    write!(writer, "\\u{:04x}", unit)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn begin_array<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.0.begin_array(writer)
+    }
+
+    fn end_array<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.0.end_array(writer)
+    }
+
+    fn begin_array_value<W>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.0.begin_array_value(writer, first)
+    }
+
+    fn end_array_value<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.0.end_array_value(writer)
+    }
+
+    fn begin_object<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.0.begin_object(writer)
+    }
+
+    fn end_object<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.0.end_object(writer)
+    }
+
+    fn begin_object_key<W>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.0.begin_object_key(writer, first)
+    }
+
+    fn begin_object_value<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.0.begin_object_value(writer)
+    }
+
+    fn end_object_value<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.0.end_object_value(writer)
+    }
+}
+
+#[cfg(all(test, any(feature = "parse", feature = "from_json")))]
+mod tests {
+    #[cfg(feature = "from_json")]
+    use super::*;
+
+    /// A snapshot of PDL and the JSON Chromium's `pdl_to_json` converter makes of it.
+    #[cfg(feature = "parse")]
+    const PDL: &str = include_str!("../../tests/data/protocol.pdl");
+    const JSON: &str = include_str!("../../tests/data/protocol.json");
+
+    #[cfg(feature = "parse")]
+    #[test]
+    fn serialize_golden() {
+        let protocol = crate::parse_str(PDL).unwrap();
+
+        assert_eq!(protocol.to_chromium_json().unwrap(), JSON);
+    }
+
+    #[cfg(feature = "from_json")]
+    #[test]
+    fn round_trip_golden() {
+        let protocol = Protocol::from_json(JSON).unwrap();
+
+        assert_eq!(protocol.to_chromium_json().unwrap(), JSON);
+    }
+
+    #[cfg(feature = "parse")]
+    #[test]
+    fn skip_what_chromium_has_no_slot_for() {
+        let protocol = crate::parse_str(
+            r#"version
+  major 1
+  minor 3
+
+internal domain Page
+  command clearDeviceMetricsOverride
+    # Use 'Emulation.clearDeviceMetricsOverride' instead
+    redirect Emulation
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            serde_json::to_value(protocol.as_chromium()).unwrap(),
+            serde_json::json!({
+                "version": { "major": "1", "minor": "3" },
+                "domains": [{
+                    "domain": "Page",
+                    "commands": [{ "name": "clearDeviceMetricsOverride", "redirect": "Emulation" }]
+                }]
+            })
+        );
+    }
+
+    #[cfg(feature = "parse")]
+    #[test]
+    fn skip_blank_descriptions() {
+        let protocol = crate::parse_str(
+            r#"version
+  major 1
+  minor 3
+
+#
+#
+domain Page
+  #
+  command enable
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            serde_json::to_value(protocol.as_chromium()).unwrap(),
+            serde_json::json!({
+                "version": { "major": "1", "minor": "3" },
+                "domains": [{ "domain": "Page", "commands": [{ "name": "enable" }] }]
+            })
+        );
+    }
+
+    #[cfg(feature = "parse")]
+    #[test]
+    fn serialize_enum_block_once() {
+        let protocol = crate::parse_str(
+            r#"version
+  major 1
+  minor 3
+
+domain Emulation
+  type MediaTypes extends array of enum
+    enum
+      screen
+      print
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            protocol.to_chromium_json().unwrap(),
+            r#"{
+    "version": {
+        "major": "1",
+        "minor": "3"
+    },
+    "domains": [
+        {
+            "domain": "Emulation",
+            "types": [
+                {
+                    "id": "MediaTypes",
+                    "type": "array",
+                    "items": {
+                        "type": "string"
+                    },
+                    "enum": [
+                        "screen",
+                        "print"
+                    ]
+                }
+            ]
+        }
+    ]
+}"#
+        );
+    }
+
+    #[cfg(feature = "parse")]
+    #[test]
+    fn serialize_in_source_order() {
+        let protocol = crate::parse_str(
+            r#"version
+  major 1
+  minor 3
+
+domain Page
+  event loadEventFired
+
+  command enable
+
+  type FrameId extends string
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            protocol.to_chromium_json().unwrap(),
+            r#"{
+    "version": {
+        "major": "1",
+        "minor": "3"
+    },
+    "domains": [
+        {
+            "domain": "Page",
+            "events": [
+                {
+                    "name": "loadEventFired"
+                }
+            ],
+            "commands": [
+                {
+                    "name": "enable"
+                }
+            ],
+            "types": [
+                {
+                    "id": "FrameId",
+                    "type": "string"
+                }
+            ]
+        }
+    ]
+}"#
+        );
+    }
+}
@@ -0,0 +1,322 @@
+use std::collections::HashSet;
+
+use crate::resolve::{ItemReference, Owner, Site};
+use crate::*;
+
+impl Protocol<'_> {
+    /// Check the `Protocol` for problems the grammar can't catch, like dangling references.
+    ///
+    /// Every diagnostic is an error, since a protocol with any of these problems can't be used as is.
+    /// A redirect to a domain which isn't defined is reported as an `unknown-redirect` error as well,
+    /// which a caller validating one protocol of several, e.g. `browser_protocol.pdl` redirecting
+    /// to `js_protocol.pdl`, can turn into a warning with `Diagnostic::downgrade`.
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut validator = Validator {
+            symbols: self.symbols(),
+            domains: self
+                .domains
+                .iter()
+                .map(|domain| domain.name.as_ref())
+                .collect(),
+            diagnostics: vec![],
+        };
+
+        validator.protocol(self);
+        validator.diagnostics
+    }
+}
+
+struct Validator<'p, 'a> {
+    symbols: SymbolTable<'p, 'a>,
+    domains: HashSet<&'p str>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Validator<'_, '_> {
+    fn protocol(&mut self, protocol: &Protocol) {
+        let mut domains = HashSet::new();
+
+        for domain in &protocol.domains {
+            if !domains.insert(domain.name.as_ref()) {
+                self.error(
+                    "duplicate-domain",
+                    format!("duplicate domain `{}`", domain.name),
+                    domain.span,
+                );
+            }
+
+            self.domain(domain);
+        }
+    }
+
+    fn domain(&mut self, domain: &Domain) {
+        let start = self.diagnostics.len();
+        let mut types = HashSet::new();
+        let mut commands = HashSet::new();
+        let mut events = HashSet::new();
+
+        for ty in &domain.types {
+            let context = format!("type `{}.{}`", domain.name, ty.id);
+
+            if !types.insert(ty.id.as_ref()) {
+                self.error("duplicate-type", format!("duplicate {}", context), ty.span);
+            }
+
+            self.ty(&ty.extends, &context);
+
+            match ty.item {
+                Some(Item::Enum(ref variants)) => {
+                    if ty.extends != Type::String {
+                        self.error(
+                            "mismatched-item",
+                            format!("{} has enum variants but doesn't extend `string`", context),
+                            ty.span,
+                        );
+                    }
+
+                    self.variants(variants, &context);
+                }
+                Some(Item::Properties(ref props)) => {
+                    if ty.extends != Type::Object {
+                        self.error(
+                            "mismatched-item",
+                            format!("{} has properties but doesn't extend `object`", context),
+                            ty.span,
+                        );
+                    }
+
+                    self.params(props, "property", &context);
+                }
+                None => {}
+            }
+        }
+
+        for cmd in &domain.commands {
+            let context = format!("command `{}.{}`", domain.name, cmd.name);
+
+            if !commands.insert(cmd.name.as_ref()) {
+                self.error(
+                    "duplicate-command",
+                    format!("duplicate {}", context),
+                    cmd.span,
+                );
+            }
+
+            if let Some(ref redirect) = cmd.redirect {
+                if !self.domains.contains(redirect.to.as_ref()) {
+                    self.error(
+                        "unknown-redirect",
+                        format!("{} redirects to unknown domain `{}`", context, redirect.to),
+                        redirect.span,
+                    );
+                }
+            }
+
+            self.params(&cmd.parameters, "parameter", &context);
+            self.params(&cmd.returns, "return value", &context);
+        }
+
+        for evt in &domain.events {
+            let context = format!("event `{}.{}`", domain.name, evt.name);
+
+            if !events.insert(evt.name.as_ref()) {
+                self.error(
+                    "duplicate-event",
+                    format!("duplicate {}", context),
+                    evt.span,
+                );
+            }
+
+            self.params(&evt.parameters, "parameter", &context);
+        }
+
+        for item_ref in domain.item_references() {
+            if self
+                .symbols
+                .resolve(&domain.name, item_ref.reference)
+                .is_none()
+            {
+                self.error(
+                    "unresolved-ref",
+                    format!(
+                        "unresolved type `{}` of {}",
+                        item_ref.reference,
+                        describe(&item_ref)
+                    ),
+                    item_ref.span(),
+                );
+            }
+        }
+
+        // the references are checked after the items, so put the diagnostics back in source order
+        self.diagnostics[start..].sort_by_key(|diag| diag.span.start);
+    }
+
+    fn params(&mut self, params: &[Param], kind: &str, context: &str) {
+        let mut names = HashSet::new();
+
+        for param in params {
+            let context = format!("{} `{}` of {}", kind, param.name, context);
+
+            if !names.insert(param.name.as_ref()) {
+                self.error(
+                    "duplicate-param",
+                    format!("duplicate {}", context),
+                    param.span,
+                );
+            }
+
+            self.ty(&param.ty, &context);
+        }
+    }
+
+    fn ty(&mut self, ty: &Type, context: &str) {
+        if let Type::Enum(variants) = ty.element() {
+            self.variants(variants, context);
+        }
+    }
+
+    fn variants(&mut self, variants: &[Variant], context: &str) {
+        let mut names = HashSet::new();
+
+        for variant in variants {
+            if !names.insert(variant.name.as_ref()) {
+                self.error(
+                    "duplicate-variant",
+                    format!("duplicate enum variant `{}` of {}", variant.name, context),
+                    variant.span,
+                );
+            }
+        }
+    }
+
+    fn error(&mut self, code: &'static str, message: String, span: Span) {
+        self.diagnostics
+            .push(Diagnostic::error(code, message, span));
+    }
+}
+
+/// Describe where a type reference is made, like ``property `url` of type `Page.Frame` ``.
+fn describe(item_ref: &ItemReference) -> String {
+    let kind = match item_ref.owner {
+        Owner::Type(_) => "type",
+        Owner::Command(_) => "command",
+        Owner::Event(_) => "event",
+    };
+    let item = format!(
+        "{} `{}.{}`",
+        kind,
+        item_ref.domain.name,
+        item_ref.owner.name()
+    );
+
+    match item_ref.site {
+        Site::Extends => item,
+        Site::Property(param) => format!("property `{}` of {}", param.name, item),
+        Site::Parameter(param) => format!("parameter `{}` of {}", param.name, item),
+        Site::Return(param) => format!("return value `{}` of {}", param.name, item),
+    }
+}
+
+#[cfg(all(test, feature = "parse"))]
+mod tests {
+    #[test]
+    fn validate_nothing() {
+        let protocol = crate::parse_str(
+            r#"version
+  major 1
+  minor 3
+
+domain Network
+  type RequestId extends string
+
+domain Page
+  type FrameId extends string
+
+  command navigate
+    parameters
+      FrameId frameId
+    returns
+      FrameId frameId
+      optional Network.RequestId requestId
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(protocol.validate(), vec![]);
+    }
+
+    #[test]
+    fn validate_problems() {
+        let protocol = crate::parse_str(
+            r#"version
+  major 1
+  minor 3
+
+domain Page
+  type FrameId extends string
+
+  type FrameId extends integer
+
+  type Kind extends object
+    enum
+      main
+      child
+      main
+
+  type Frame extends string
+    properties
+      FrameId id
+      array of LoaderId loaderIds
+      Network.Headers headers
+      string id
+
+  command navigate
+    redirect Emulation
+    parameters
+      optional enum transition
+        link
+        link
+
+  command navigate
+
+  event loaded
+
+  event loaded
+
+domain Page
+"#,
+        )
+        .unwrap();
+        let diagnostics = protocol.validate();
+
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|diag| diag.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "8:3: error[duplicate-type]: duplicate type `Page.FrameId`",
+                "10:3: error[mismatched-item]: type `Page.Kind` has enum variants but doesn't extend `string`",
+                "14:7: error[duplicate-variant]: duplicate enum variant `main` of type `Page.Kind`",
+                "16:3: error[mismatched-item]: type `Page.Frame` has properties but doesn't extend `object`",
+                "19:7: error[unresolved-ref]: unresolved type `LoaderId` of property `loaderIds` of type `Page.Frame`",
+                "20:7: error[unresolved-ref]: unresolved type `Network.Headers` of property `headers` of type `Page.Frame`",
+                "21:7: error[duplicate-param]: duplicate property `id` of type `Page.Frame`",
+                "24:5: error[unknown-redirect]: command `Page.navigate` redirects to unknown domain `Emulation`",
+                "28:9: error[duplicate-variant]: duplicate enum variant `link` of parameter `transition` of command `Page.navigate`",
+                "30:3: error[duplicate-command]: duplicate command `Page.navigate`",
+                "34:3: error[duplicate-event]: duplicate event `Page.loaded`",
+                "36:1: error[duplicate-domain]: duplicate domain `Page`",
+            ]
+        );
+        assert_eq!(
+            diagnostics.iter().filter(|diag| diag.is_error()).count(),
+            12
+        );
+        assert_eq!(
+            diagnostics[7].clone().downgrade().to_string(),
+            "24:5: warning[unknown-redirect]: command `Page.navigate` redirects to unknown domain `Emulation`"
+        );
+    }
+}